@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, fmt};
+use thiserror::Error;
+
+/// A range of some measurement, like a cauldron's temperature, given a value like a `Heat`.
+/// It starts at `min` and runs up to wherever the next band starts.
+pub trait Band {
+    type Value: Copy + fmt::Debug + fmt::Display;
+
+    fn value(&self) -> Self::Value;
+    fn min(&self) -> f32;
+}
+
+#[derive(Error, Debug, PartialEq)]
+#[error("the {value} band has to start above the band before it")]
+pub struct BandOrderError<T: fmt::Debug + fmt::Display> {
+    pub value: T,
+}
+
+/// Bands from the lowest up, each starting above the one before it.
+/// Below the first band there's no value at all.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(
+    try_from = "Vec<B>",
+    into = "Vec<B>",
+    bound(
+        serialize = "B: Band + Clone + Serialize",
+        deserialize = "B: Band + Deserialize<'de>"
+    )
+)]
+pub struct Bands<B>(Vec<B>);
+
+impl<B: Band> Bands<B> {
+    pub fn new(bands: Vec<B>) -> Result<Bands<B>, BandOrderError<B::Value>> {
+        for pair in bands.windows(2) {
+            if pair[1].min() <= pair[0].min() {
+                return Err(BandOrderError {
+                    value: pair[1].value(),
+                });
+            }
+        }
+        Ok(Bands(bands))
+    }
+
+    /// The value of the band that `measure` falls in, which is None below the first band.
+    pub fn get(&self, measure: f32) -> Option<B::Value> {
+        self.0
+            .iter()
+            .rev()
+            .find(|band| measure >= band.min())
+            .map(Band::value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &B> {
+        self.0.iter()
+    }
+}
+
+impl<B> Default for Bands<B> {
+    fn default() -> Bands<B> {
+        Bands(Vec::new())
+    }
+}
+
+impl<B: Band> TryFrom<Vec<B>> for Bands<B> {
+    type Error = BandOrderError<B::Value>;
+
+    fn try_from(bands: Vec<B>) -> Result<Bands<B>, BandOrderError<B::Value>> {
+        Bands::new(bands)
+    }
+}
+
+impl<B> From<Bands<B>> for Vec<B> {
+    fn from(bands: Bands<B>) -> Vec<B> {
+        bands.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug)]
+    struct Grade(&'static str, f32);
+
+    impl Band for Grade {
+        type Value = &'static str;
+
+        fn value(&self) -> &'static str {
+            self.0
+        }
+
+        fn min(&self) -> f32 {
+            self.1
+        }
+    }
+
+    #[test]
+    fn test_band_lookup() {
+        let bands = Bands::new(vec![Grade("C", 50.0), Grade("B", 70.0), Grade("A", 90.0)]).unwrap();
+        assert_eq!(bands.get(49.9), None);
+        assert_eq!(bands.get(50.0), Some("C"));
+        assert_eq!(bands.get(89.9), Some("B"));
+        assert_eq!(bands.get(100.0), Some("A"));
+    }
+
+    #[test]
+    fn test_band_order() {
+        assert_eq!(
+            Bands::new(vec![Grade("B", 70.0), Grade("C", 50.0)]).unwrap_err(),
+            BandOrderError { value: "C" }
+        );
+        assert_eq!(
+            Bands::new(vec![Grade("B", 70.0), Grade("A", 70.0)]).unwrap_err(),
+            BandOrderError { value: "A" }
+        );
+    }
+}
@@ -1,15 +1,23 @@
+use bevy::prelude::Entity;
 use serde::{Deserialize, Serialize};
-use strum::EnumIter;
+use strum::{Display, EnumIter, EnumString};
 
 /// Heat may or may not be present on a Cauldron,
 /// If it's not present, no reaction should occur.
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize, EnumIter)]
+/// A cauldron with a `Temperature` has the heat of the band its temperature is in.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize, Display, EnumIter, EnumString,
+)]
 pub enum Heat {
+    Warm,
     Simmering,
     Boiling,
+    Scorching,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize, EnumIter)]
+#[derive(
+    Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize, Display, EnumIter, EnumString,
+)]
 pub enum StirMethod {
     /// As opposed to Heat, reactions may occur when there's no stirring,
     /// represented by this variant.
@@ -22,5 +30,33 @@ pub enum StirMethod {
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
 pub struct Cauldron;
 
+/// A container for compounds separated out of a cauldron, like by distillation.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+pub struct Flask;
+
+/// A container for the compounds held back by a `Filter`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+pub struct Residue;
+
+/// A container whose contents slowly ferment over days, with no heat or stirring.
+/// Each day ferments with an rng seeded from `seed` and the day,
+/// so a fermenter ends up the same whether its days pass one by one or all at once.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+pub struct Fermenter {
+    pub seed: u64,
+    /// How many days the contents have been fermenting for.
+    pub age: u32,
+}
+
+impl Fermenter {
+    pub fn new(seed: u64) -> Fermenter {
+        Fermenter { seed, age: 0 }
+    }
+}
+
+/// The container that a compound is in, like a `Cauldron`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct ContainedIn(pub Entity);
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
 pub struct RankDisplayer;
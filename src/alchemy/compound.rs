@@ -1,8 +1,14 @@
-use crate::alchemy::{element::*, element_counts::*, AltonWeighable};
+use crate::alchemy::{
+    element::*,
+    element_counts::*,
+    weighting::{OutcomeWeighting, UniformWeighting},
+    AltonWeighable,
+};
 use nom::combinator;
-use rand::seq::IteratorRandom;
+use rand::{seq::SliceRandom, Rng};
 use serde::{Deserialize, Serialize};
 use std::{
+    cmp,
     collections::HashSet,
     convert::{TryFrom, TryInto},
     fmt, hash,
@@ -14,8 +20,41 @@ use thiserror::Error;
 pub enum CompoundError {
     #[error("invalid alton count in alchemical: {size}")]
     SizeError { size: u32 },
-    #[error("failed to parse alchemical")]
-    ParseError,
+    #[error("{}", display_parse_error("alchemical", .input, *.offset, .found, .expected))]
+    ParseError {
+        input: String,
+        /// Byte offset of the problem in `input`.
+        offset: usize,
+        /// The character at `offset`, or None at the end of `input`.
+        found: Option<char>,
+        expected: String,
+    },
+}
+
+/// Describe a parse error, with a caret under where it went wrong, like:
+///
+/// ```text
+/// failed to parse alchemical at column 3: expected an element that can follow D, found 'A'
+///     D3A
+///       ^
+/// ```
+pub(crate) fn display_parse_error(
+    what: &str,
+    input: &str,
+    offset: usize,
+    found: &Option<char>,
+    expected: &str,
+) -> String {
+    let column = input[..offset].chars().count() + 1;
+    format!(
+        "failed to parse {} at column {}: expected {}, found {}\n    {}\n    {}^",
+        what,
+        column,
+        expected,
+        found.map_or("the end".to_string(), |c| format!("{:?}", c)),
+        input,
+        " ".repeat(column - 1)
+    )
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
@@ -30,7 +69,7 @@ impl<const W: u32> fmt::Display for Alchemical<W> {
             .element_counts
             .into_iter()
             .collect::<Vec<(Element, u32)>>();
-        element_count_pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        element_count_pairs.sort_by_key(|(e, _)| *e);
 
         write!(
             f,
@@ -53,14 +92,42 @@ impl<const W: u32> hash::Hash for Alchemical<W> {
     }
 }
 
+/// Orders alchemicals by their element counts, comparing the lightest element first.
+/// Only really meaningful for giving collections of alchemicals a deterministic order.
+impl<const W: u32> Ord for Alchemical<W> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        Element::iter()
+            .map(|e| {
+                self.element_counts
+                    .get(&e)
+                    .cmp(&other.element_counts.get(&e))
+            })
+            .find(|ordering| *ordering != cmp::Ordering::Equal)
+            .unwrap_or(cmp::Ordering::Equal)
+    }
+}
+
+impl<const W: u32> PartialOrd for Alchemical<W> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// How forgiving parsing an alchemical's notation is.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum ParseMode {
+    /// Only the canonical notation, the same as `Display`, like "3AD".
+    Strict,
+    /// Elements in any order and of any case, repeated elements, whitespace,
+    /// and multiplied groups, like "D3A", "AAAD", "3a d" or "2(AB)A".
+    Lenient,
+}
+
 impl<const W: u32> FromStr for Alchemical<W> {
     type Err = CompoundError;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        match combinator::all_consuming(element_counts_parser)(value) {
-            Ok((_, element_counts)) => Ok(Alchemical::try_from(element_counts)?),
-            _ => Err(CompoundError::ParseError),
-        }
+        Alchemical::from_str_with(value, ParseMode::Strict)
     }
 }
 
@@ -91,21 +158,94 @@ impl<const W: u32> AltonWeighable for Alchemical<W> {
 }
 
 impl<const W: u32> Alchemical<W> {
-    pub fn try_from_element_counts(
-        a: u32,
-        b: u32,
-        c: u32,
-        d: u32,
-        e: u32,
-    ) -> Result<Alchemical<W>, CompoundError> {
-        let mut element_counts = ElementCounts::new();
-        element_counts.insert(Element::A, a);
-        element_counts.insert(Element::B, b);
-        element_counts.insert(Element::C, c);
-        element_counts.insert(Element::D, d);
-        element_counts.insert(Element::E, e);
-
-        Ok(Alchemical::try_from(element_counts)?)
+    pub fn from_str_with(value: &str, mode: ParseMode) -> Result<Alchemical<W>, CompoundError> {
+        let parsed = match mode {
+            ParseMode::Strict => combinator::all_consuming(element_counts_parser)(value),
+            ParseMode::Lenient => combinator::all_consuming(lenient_element_counts_parser)(value),
+        };
+        match parsed {
+            Ok((_, element_counts)) => Ok(Alchemical::try_from(element_counts)?),
+            _ => Err(Self::diagnose_parse_error(value, mode)),
+        }
+    }
+
+    /// Find where and why `value` isn't valid notation.
+    fn diagnose_parse_error(value: &str, mode: ParseMode) -> CompoundError {
+        let (remaining, element_counts) = match mode {
+            ParseMode::Strict => element_counts_parser(value),
+            ParseMode::Lenient => lenient_element_counts_parser(value),
+        }
+        .unwrap_or((value, ElementCounts::new()));
+
+        let after_count = remaining.trim_start_matches(|c: char| c.is_ascii_digit());
+        let next_element = Element::iter().find_map(|e| element_parser_maker(e)(after_count).ok());
+        let last_element = element_counts.keys().max();
+
+        let (rest, expected) = match (mode, next_element, last_element) {
+            (ParseMode::Strict, Some((_, next)), Some(last)) if next <= *last => {
+                (after_count, format!("an element that can follow {}", last))
+            }
+            _ if after_count.len() < remaining.len() => {
+                (after_count, "an element after the count".to_string())
+            }
+            _ => (remaining, "an element".to_string()),
+        };
+
+        CompoundError::ParseError {
+            input: value.to_string(),
+            offset: value.len() - rest.len(),
+            found: rest.chars().next(),
+            expected,
+        }
+    }
+
+    /// `counts` are given in element table order, lightest element first.
+    /// Elements past the end of `counts` are treated as absent.
+    pub fn try_from_element_counts(counts: &[u32]) -> Result<Alchemical<W>, CompoundError> {
+        Alchemical::try_from(
+            Element::iter()
+                .zip(counts.iter().copied())
+                .collect::<ElementCounts>(),
+        )
+    }
+
+    /// Every valid alchemical of weight `W`, in ascending order.
+    pub fn all() -> impl Iterator<Item = Alchemical<W>> {
+        let elements = Element::iter().collect::<Vec<Element>>();
+        let mut all = Self::all_recursion(&elements, ElementCounts::new(), W);
+        all.sort();
+        all.into_iter()
+    }
+
+    /// Create every alchemical made of `element_counts` plus some of the given `elements`,
+    /// where those elements add up to `remaining_weight`.
+    /// This is meant to be called recursively, handling one element per step.
+    fn all_recursion(
+        elements: &[Element],
+        element_counts: ElementCounts,
+        remaining_weight: u32,
+    ) -> Vec<Alchemical<W>> {
+        match elements.split_first() {
+            None if remaining_weight == 0 => vec![element_counts
+                .try_into()
+                .expect("Alchemicals with no remaining weight should be valid")],
+            None => Vec::new(),
+            Some((element, remaining_elements)) => (0..=remaining_weight / element.weight())
+                .flat_map(|count| {
+                    let mut element_counts = element_counts.clone();
+                    element_counts.insert(*element, count);
+                    Self::all_recursion(
+                        remaining_elements,
+                        element_counts,
+                        remaining_weight - count * element.weight(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn element_count(&self, element: Element) -> u32 {
+        self.element_counts.get(&element).copied().unwrap_or(0)
     }
 
     fn validate(&self) -> bool {
@@ -123,17 +263,57 @@ impl<const W: u32> Alchemical<W> {
     }
 
     pub fn react(&mut self, other: &mut Alchemical<W>) {
-        let possible_reactions = self.set_of_possible_reactions(&other);
+        self.react_with_rng(other, &mut rand::thread_rng());
+    }
+
+    /// Same as `react`, but the outcome is chosen using the given `rng`.
+    /// Reacting the same alchemicals with identically seeded rngs gives identical results.
+    pub fn react_with_rng<R: Rng + ?Sized>(&mut self, other: &mut Alchemical<W>, rng: &mut R) {
+        self.react_weighted(other, &UniformWeighting, rng);
+    }
 
-        let (self_reaction, other_reaction) = possible_reactions
+    /// Same as `react_with_rng`, but each possible outcome is as likely as `weighting` says.
+    /// If no outcome has a positive weight, nothing happens.
+    pub fn react_weighted<O, R>(&mut self, other: &mut Alchemical<W>, weighting: &O, rng: &mut R)
+    where
+        O: OutcomeWeighting<W> + ?Sized,
+        R: Rng + ?Sized,
+    {
+        // Sorted since the iteration order of a HashSet differs between runs.
+        let mut possible_reactions = self
+            .set_of_possible_reactions(other)
             .into_iter()
-            .choose(&mut rand::thread_rng())
-            .expect("There should at least be two reactions: the current state and its inverse");
+            .collect::<Vec<(Alchemical<W>, Alchemical<W>)>>();
+        possible_reactions.sort();
 
-        *self = self_reaction;
-        *other = other_reaction;
-        self.clean();
-        other.clean();
+        self.react_among(other, &possible_reactions, weighting, rng);
+    }
+
+    /// Replace `self` and `other` with one of `possible_reactions`, chosen according to
+    /// `weighting`. `possible_reactions` should be sorted for the choice to be reproducible.
+    pub(crate) fn react_among<O, R>(
+        &mut self,
+        other: &mut Alchemical<W>,
+        possible_reactions: &[(Alchemical<W>, Alchemical<W>)],
+        weighting: &O,
+        rng: &mut R,
+    ) where
+        O: OutcomeWeighting<W> + ?Sized,
+        R: Rng + ?Sized,
+    {
+        let reaction = possible_reactions
+            .choose_weighted(rng, |(self_reaction, other_reaction)| {
+                weighting.weight((self, other), (self_reaction, other_reaction))
+            })
+            .ok()
+            .cloned();
+
+        if let Some((self_reaction, other_reaction)) = reaction {
+            *self = self_reaction;
+            *other = other_reaction;
+            self.clean();
+            other.clean();
+        }
     }
 
     /// Create a set of all possible redistributions of elements in an ElementCounts into two
@@ -177,8 +357,7 @@ impl<const W: u32> Alchemical<W> {
             let (selected_element, selected_element_count) = total_element_counts
                 .clone()
                 .into_iter()
-                .filter(|(_, v)| *v > 0)
-                .next()
+                .find(|(_, v)| *v > 0)
                 .expect("We've already checked for an empty total_element_counts");
 
             // Cloning to do this subtraction immutably,
@@ -239,56 +418,65 @@ where
         })
 }
 
-pub type Compound = Alchemical<7>;
+/// The alton weight of every compound.
+pub const COMPOUND_WEIGHT: u32 = 7;
+
+pub type Compound = Alchemical<COMPOUND_WEIGHT>;
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn element(symbol: &str) -> Element {
+        Element::from_symbol(symbol).expect("Test elements should be in the element table")
+    }
 
     #[test]
     fn test_alchemical_equality() -> Result<(), CompoundError> {
         assert_eq!(
-            Alchemical::<7>::try_from_element_counts(0, 1, 0, 0, 1)?,
-            Alchemical::<7>::try_from_element_counts(0, 1, 0, 0, 1)?
+            Alchemical::<7>::try_from_element_counts(&[0, 1, 0, 0, 1])?,
+            Alchemical::<7>::try_from_element_counts(&[0, 1, 0, 0, 1])?
         );
         Ok(())
     }
 
     #[test]
     fn test_alchemical_appropriate_size_ok() -> Result<(), CompoundError> {
-        Alchemical::<7>::try_from_element_counts(7, 0, 0, 0, 0)?;
-        Alchemical::<7>::try_from_element_counts(2, 0, 0, 0, 1)?;
-        Alchemical::<7>::try_from_element_counts(3, 0, 0, 1, 0)?;
-        Alchemical::<7>::try_from_element_counts(0, 0, 1, 1, 0)?;
+        Alchemical::<7>::try_from_element_counts(&[7, 0, 0, 0, 0])?;
+        Alchemical::<7>::try_from_element_counts(&[2, 0, 0, 0, 1])?;
+        Alchemical::<7>::try_from_element_counts(&[3, 0, 0, 1, 0])?;
+        Alchemical::<7>::try_from_element_counts(&[0, 0, 1, 1, 0])?;
         Ok(())
     }
 
     #[test]
     fn test_alchemical_inappropriate_size_fails() {
         assert_eq!(
-            Alchemical::<7>::try_from_element_counts(5, 0, 0, 0, 0),
+            Alchemical::<7>::try_from_element_counts(&[5, 0, 0, 0, 0]),
             Err(CompoundError::SizeError { size: 5 })
         );
         assert_eq!(
-            Alchemical::<7>::try_from_element_counts(0, 0, 0, 0, 2),
+            Alchemical::<7>::try_from_element_counts(&[0, 0, 0, 0, 2]),
             Err(CompoundError::SizeError { size: 10 })
         );
         assert_eq!(
-            Alchemical::<7>::try_from_element_counts(0, 1, 1, 1, 0),
+            Alchemical::<7>::try_from_element_counts(&[0, 1, 1, 1, 0]),
             Err(CompoundError::SizeError { size: 9 })
         );
         assert_eq!(
-            Alchemical::<7>::try_from_element_counts(0, 1, 0, 1, 0),
+            Alchemical::<7>::try_from_element_counts(&[0, 1, 0, 1, 0]),
             Err(CompoundError::SizeError { size: 6 })
         );
     }
 
     #[test]
     fn test_alchemical_reaction_validity() -> Result<(), CompoundError> {
-        let mut alchemical_a = Alchemical::<7>::try_from_element_counts(1, 3, 0, 0, 0)?;
-        let mut alchemical_b = Alchemical::<7>::try_from_element_counts(2, 1, 1, 0, 0)?;
-        let mut alchemical_c = Alchemical::<7>::try_from_element_counts(0, 1, 0, 0, 1)?;
-        let mut alchemical_d = Alchemical::<7>::try_from_element_counts(0, 0, 1, 1, 0)?;
+        let mut alchemical_a = Alchemical::<7>::try_from_element_counts(&[1, 3, 0, 0, 0])?;
+        let mut alchemical_b = Alchemical::<7>::try_from_element_counts(&[2, 1, 1, 0, 0])?;
+        let mut alchemical_c = Alchemical::<7>::try_from_element_counts(&[0, 1, 0, 0, 1])?;
+        let mut alchemical_d = Alchemical::<7>::try_from_element_counts(&[0, 0, 1, 1, 0])?;
 
         println!(
             "{} {} {} {}",
@@ -332,19 +520,60 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_seeded_reactions_are_reproducible() -> Result<(), CompoundError> {
+        fn brew(seed: u64) -> Result<Vec<Alchemical<7>>, CompoundError> {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut alchemicals: Vec<Alchemical<7>> = vec![
+                "A3B".parse()?,
+                "2ABC".parse()?,
+                "BE".parse()?,
+                "CD".parse()?,
+            ];
+            for _ in 0..10 {
+                let (left, right) = alchemicals.split_at_mut(2);
+                left[0].react_with_rng(&mut right[0], &mut rng);
+                left[1].react_with_rng(&mut right[1], &mut rng);
+                alchemicals.rotate_left(1);
+            }
+            Ok(alchemicals)
+        }
+
+        assert_eq!(
+            brew(7)?,
+            vec![
+                "A3B".parse()?,
+                "A2C".parse()?,
+                "BE".parse()?,
+                "ABD".parse()?
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_outcome_reaction_is_exact() -> Result<(), CompoundError> {
+        let mut left: Alchemical<7> = "7A".parse()?;
+        let mut right: Alchemical<7> = "7A".parse()?;
+        left.react_with_rng(&mut right, &mut StdRng::seed_from_u64(0));
+        assert_eq!(left, "7A".parse()?);
+        assert_eq!(right, "7A".parse()?);
+        Ok(())
+    }
+
     #[test]
     fn test_alchemical_parsing() -> Result<(), CompoundError> {
         assert_eq!(
             Alchemical::<7>::from_str("2ABC")?,
-            Alchemical::<7>::try_from_element_counts(2, 1, 1, 0, 0)?
+            Alchemical::<7>::try_from_element_counts(&[2, 1, 1, 0, 0])?
         );
         assert_eq!(
             Alchemical::<7>::from_str("BE")?,
-            Alchemical::<7>::try_from_element_counts(0, 1, 0, 0, 1)?
+            Alchemical::<7>::try_from_element_counts(&[0, 1, 0, 0, 1])?
         );
         assert_eq!(
             Alchemical::<7>::from_str("3A1D")?,
-            Alchemical::<7>::try_from_element_counts(3, 0, 0, 1, 0)?
+            Alchemical::<7>::try_from_element_counts(&[3, 0, 0, 1, 0])?
         );
         Ok(())
     }
@@ -353,11 +582,21 @@ mod tests {
     fn test_alchemical_parsing_failures() {
         assert_eq!(
             Alchemical::<7>::from_str("D3A"),
-            Err(CompoundError::ParseError)
+            Err(CompoundError::ParseError {
+                input: "D3A".to_string(),
+                offset: 2,
+                found: Some('A'),
+                expected: "an element that can follow D".to_string(),
+            })
         );
         assert_eq!(
             Alchemical::<7>::from_str("FAF"),
-            Err(CompoundError::ParseError)
+            Err(CompoundError::ParseError {
+                input: "FAF".to_string(),
+                offset: 0,
+                found: Some('F'),
+                expected: "an element".to_string(),
+            })
         );
         assert_eq!(
             Alchemical::<7>::from_str("ABC"),
@@ -369,6 +608,73 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_alchemical_lenient_parsing() -> Result<(), CompoundError> {
+        let canonical: Alchemical<7> = "3AD".parse()?;
+        for notation in [
+            "3AD",
+            "D3A",
+            "AAAD",
+            "A2AD",
+            " 3a d ",
+            "d 3 A",
+            "(3A)D",
+            "A(A(A D))",
+        ] {
+            assert_eq!(
+                Alchemical::<7>::from_str_with(notation, ParseMode::Lenient)?,
+                canonical,
+                "{}",
+                notation
+            );
+        }
+        assert!(matches!(
+            Alchemical::<7>::from_str_with("D3Q", ParseMode::Lenient),
+            Err(CompoundError::ParseError { offset: 2, .. })
+        ));
+        assert_eq!(
+            Alchemical::<7>::from_str_with("DDA", ParseMode::Lenient),
+            Err(CompoundError::SizeError { size: 9 })
+        );
+        assert_eq!(
+            Alchemical::<7>::from_str_with("2(AB)A", ParseMode::Lenient)?,
+            "3A2B".parse()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_error_positions() {
+        let error = |notation: &str| match Alchemical::<7>::from_str(notation) {
+            Err(CompoundError::ParseError {
+                offset,
+                found,
+                expected,
+                ..
+            }) => (offset, found, expected),
+            result => panic!("{} should fail to parse, got {:?}", notation, result),
+        };
+        assert_eq!(
+            error("2AA"),
+            (2, Some('A'), "an element that can follow A".to_string())
+        );
+        assert_eq!(
+            error("A3"),
+            (2, None, "an element after the count".to_string())
+        );
+        assert_eq!(error("A B"), (1, Some(' '), "an element".to_string()));
+        assert_eq!(error("7A!"), (2, Some('!'), "an element".to_string()));
+    }
+
+    #[test]
+    fn test_parse_error_display() {
+        assert_eq!(
+            Alchemical::<7>::from_str("DB").unwrap_err().to_string(),
+            "failed to parse alchemical at column 2: expected an element that can follow D, \
+             found 'B'\n    DB\n     ^"
+        );
+    }
+
     #[test]
     fn test_list_possible_reactions() -> Result<(), CompoundError> {
         let left_alchemical: Alchemical<7> = "2AE".parse()?;
@@ -397,12 +703,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_all_alchemicals() {
+        let all = Alchemical::<7>::all().collect::<Vec<Alchemical<7>>>();
+        assert_eq!(all.len(), 13);
+        assert!(all.iter().all(|alchemical| alchemical.validate()));
+        assert_eq!(all.iter().collect::<HashSet<&Alchemical<7>>>().len(), 13);
+
+        assert_eq!(Alchemical::<1>::all().count(), 1);
+        assert_eq!(Alchemical::<0>::all().count(), 1);
+    }
+
     #[test]
     fn test_impossible_reaction_recursion_gives_empty_list() {
         // Can't be divided into two
         let mut total_element_counts: ElementCounts = ElementCounts::new();
-        total_element_counts.insert(Element::C, 5);
-        total_element_counts.insert(Element::E, 1);
+        total_element_counts.insert(element("C"), 5);
+        total_element_counts.insert(element("E"), 1);
         assert_eq!(
             HashSet::new(),
             Alchemical::<10>::reaction_recursion(
@@ -414,8 +731,8 @@ mod tests {
 
         // Exceeds desired weight
         total_element_counts.clear();
-        total_element_counts.insert(Element::A, 4);
-        total_element_counts.insert(Element::B, 2);
+        total_element_counts.insert(element("A"), 4);
+        total_element_counts.insert(element("B"), 2);
         assert_eq!(
             HashSet::new(),
             Alchemical::<2>::reaction_recursion(
@@ -427,9 +744,9 @@ mod tests {
 
         // Under desired weight
         total_element_counts.clear();
-        total_element_counts.insert(Element::A, 3);
-        total_element_counts.insert(Element::B, 2);
-        total_element_counts.insert(Element::C, 1);
+        total_element_counts.insert(element("A"), 3);
+        total_element_counts.insert(element("B"), 2);
+        total_element_counts.insert(element("C"), 1);
         assert_eq!(
             HashSet::new(),
             Alchemical::<11>::reaction_recursion(
@@ -1,48 +1,70 @@
-use crate::{alchemy::BrewingPlugin, AppState};
+use crate::{
+    alchemy::{pattern::CompoundPattern, BrewingPlugin},
+    AppState,
+};
 use bevy::prelude::*;
 
 pub struct BrewingPluginDebug;
 
+/// Only compounds matching this pattern are shown in the rank display.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct RankDisplayPattern(pub CompoundPattern);
+
 impl Plugin for BrewingPluginDebug {
     fn build(&self, app: &mut AppBuilder) {
         app.add_plugin(BrewingPlugin)
+            .init_resource::<RankDisplayPattern>()
+            // Lets designers see their changes to the reaction rules without restarting.
+            .add_system(crate::alchemy::systems::reloading_reaction_rule_layers.system())
             .add_system_set(
                 SystemSet::on_enter(AppState::Brewing)
-                    .with_system(transitions::spawn_test_compounds.system())
                     .with_system(transitions::spawn_cauldron.system())
+                    .with_system(transitions::spawn_mortar.system())
                     .with_system(transitions::spawn_rank_display.system())
                     .with_system(transitions::spawn_camera.system()),
             )
             .add_system_set(
                 SystemSet::on_update(AppState::Brewing)
                     .with_system(systems::compound_rank_display.system())
-                    .with_system(systems::reaction_test_input.system()),
+                    .with_system(systems::reaction_test_input.system())
+                    .with_system(systems::stir_test_input.system())
+                    .with_system(systems::bottle_test_input.system())
+                    .with_system(systems::log_bottled_potions.system())
+                    .with_system(systems::mortar_test_input.system())
+                    .with_system(systems::log_expressed_ingredients.system()),
             );
     }
 }
 
 mod transitions {
-    use crate::alchemy::{components::*, compound::Compound};
+    use crate::alchemy::{
+        components::*, ingredient::AddIngredient, mortar::Mortar, stirring::StirGesture,
+        temperature::Temperature,
+    };
     use bevy::prelude::*;
-    use std::str::FromStr;
 
-    pub fn spawn_test_compounds(mut commands: Commands) {
-        for _ in 0..20 {
-            commands.spawn().insert(Compound::from_str("A3B").unwrap());
-        }
-        for _ in 0..30 {
-            commands.spawn().insert(Compound::from_str("7A").unwrap());
-        }
-        for _ in 0..30 {
-            commands.spawn().insert(Compound::from_str("BE").unwrap());
+    /// Spawns a cauldron with some test ingredients in it.
+    pub fn spawn_cauldron(mut commands: Commands, mut add_events: EventWriter<AddIngredient>) {
+        let cauldron = commands
+            .spawn()
+            .insert(Cauldron)
+            .insert(StirMethod::ZeroStir)
+            .insert(StirGesture::default())
+            .insert(Temperature::at(20.0))
+            .id();
+
+        for (count, ingredient) in [(2, "Moonleaf"), (3, "Emberroot"), (3, "Skyglass")] {
+            for _ in 0..count {
+                add_events.send(AddIngredient {
+                    ingredient: ingredient.to_string(),
+                    cauldron,
+                });
+            }
         }
     }
 
-    pub fn spawn_cauldron(mut commands: Commands) {
-        commands
-            .spawn()
-            .insert(Cauldron)
-            .insert(StirMethod::ZeroStir);
+    pub fn spawn_mortar(mut commands: Commands) {
+        commands.spawn().insert(Mortar::default());
     }
 
     pub fn spawn_camera(mut commands: Commands) {
@@ -69,17 +91,29 @@ mod transitions {
 }
 
 mod systems {
-    use crate::alchemy::{components::*, compound::Compound};
+    use super::RankDisplayPattern;
+    use crate::alchemy::{
+        components::*,
+        compound::Compound,
+        mortar::{ExpressIngredient, Expressed, Mortar, StrikePestle},
+        potion::{BottleCauldron, PotionBottled},
+        stirring::StirInput,
+        temperature::{AddFuel, PumpBellows},
+    };
     use bevy::prelude::*;
     use std::{cmp::Ordering, collections::HashMap};
 
     pub fn compound_rank_display(
         compound_query: Query<&Compound>,
         mut rank_display_query: Query<&mut Text, With<RankDisplayer>>,
+        rank_display_pattern: Res<RankDisplayPattern>,
     ) {
         for mut rank_text in rank_display_query.iter_mut() {
             let mut compound_counter: HashMap<String, u32> = HashMap::new();
-            for compound in compound_query.iter() {
+            for compound in compound_query
+                .iter()
+                .filter(|compound| rank_display_pattern.0.matches(*compound))
+            {
                 *compound_counter.entry(compound.to_string()).or_insert(0) += 1;
             }
 
@@ -100,37 +134,95 @@ mod systems {
         }
     }
 
+    /// B pumps the bellows and F puts more fuel on the fire, to keep the cauldron at the right heat.
     pub fn reaction_test_input(
-        mut cauldron_query: Query<(Entity, Option<&mut Heat>, &mut StirMethod), With<Cauldron>>,
-        mut commands: Commands,
+        cauldron_query: Query<Entity, With<Cauldron>>,
+        mut fuel_events: EventWriter<AddFuel>,
+        mut bellows_events: EventWriter<PumpBellows>,
         input: Res<Input<KeyCode>>,
     ) {
-        if let Some((entity, heat, mut stir_method)) = cauldron_query.iter_mut().next() {
-            if input.just_pressed(KeyCode::Key0) {
-                *stir_method = StirMethod::ZeroStir;
-            } else if input.just_pressed(KeyCode::Key1) {
-                *stir_method = StirMethod::SingleStir;
-            } else if input.just_pressed(KeyCode::Key2) {
-                *stir_method = StirMethod::DoubleStir;
-            } else if input.just_pressed(KeyCode::Key4) {
-                *stir_method = StirMethod::QuadrupleStir;
+        if let Some(cauldron) = cauldron_query.iter().next() {
+            if input.just_pressed(KeyCode::B) {
+                bellows_events.send(PumpBellows(cauldron));
+            }
+            if input.just_pressed(KeyCode::F) {
+                fuel_events.send(AddFuel {
+                    cauldron,
+                    fuel: 5.0,
+                });
             }
+        }
+    }
 
-            if input.pressed(KeyCode::B) {
-                if let Some(mut heat) = heat {
-                    *heat = Heat::Boiling;
-                } else {
-                    commands.entity(entity).insert(Heat::Boiling);
-                }
-            } else if input.pressed(KeyCode::S) {
-                if let Some(mut heat) = heat {
-                    *heat = Heat::Simmering;
-                } else {
-                    commands.entity(entity).insert(Heat::Simmering);
-                }
-            } else if heat.is_some() {
-                commands.entity(entity).remove::<Heat>();
+    /// Dragging the mouse around in circles stirs the cauldron.
+    pub fn stir_test_input(
+        cauldron_query: Query<Entity, With<Cauldron>>,
+        mut stir_events: EventWriter<StirInput>,
+        mouse_input: Res<Input<MouseButton>>,
+        windows: Res<Windows>,
+    ) {
+        if let (Some(cauldron), Some(position)) = (
+            cauldron_query.iter().next(),
+            windows
+                .get_primary()
+                .and_then(|window| window.cursor_position()),
+        ) {
+            if mouse_input.pressed(MouseButton::Left) {
+                stir_events.send(StirInput { cauldron, position });
             }
         }
     }
+
+    pub fn bottle_test_input(
+        cauldron_query: Query<Entity, With<Cauldron>>,
+        mut bottle_events: EventWriter<BottleCauldron>,
+        input: Res<Input<KeyCode>>,
+    ) {
+        if input.just_pressed(KeyCode::P) {
+            if let Some(cauldron) = cauldron_query.iter().next() {
+                bottle_events.send(BottleCauldron(cauldron));
+            }
+        }
+    }
+
+    /// Space strikes the pestle, in time with the mortar's rhythm for the best pressing,
+    /// and E presses some Moonleaf into the cauldron.
+    pub fn mortar_test_input(
+        cauldron_query: Query<Entity, With<Cauldron>>,
+        mortar_query: Query<Entity, With<Mortar>>,
+        mut strike_events: EventWriter<StrikePestle>,
+        mut express_events: EventWriter<ExpressIngredient>,
+        input: Res<Input<KeyCode>>,
+    ) {
+        if let (Some(cauldron), Some(mortar)) =
+            (cauldron_query.iter().next(), mortar_query.iter().next())
+        {
+            if input.just_pressed(KeyCode::Space) {
+                strike_events.send(StrikePestle(mortar));
+            } else if input.just_pressed(KeyCode::E) {
+                express_events.send(ExpressIngredient {
+                    ingredient: "Moonleaf".to_string(),
+                    mortar,
+                    container: cauldron,
+                });
+            }
+        }
+    }
+
+    pub fn log_expressed_ingredients(mut expressed_events: EventReader<Expressed>) {
+        for Expressed { quality, .. } in expressed_events.iter() {
+            info!("Pressed an ingredient with {:.0}% quality", quality * 100.0);
+        }
+    }
+
+    pub fn log_bottled_potions(mut bottled_events: EventReader<PotionBottled>) {
+        for PotionBottled { potion, .. } in bottled_events.iter() {
+            info!(
+                "Bottled a potion of {} compounds, {:.0}% pure: {:?}",
+                potion.volume,
+                potion.purity * 100.0,
+                potion.effects.0
+            );
+        }
+    }
 }
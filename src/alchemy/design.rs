@@ -0,0 +1,384 @@
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use std::{fmt, fs, io, path::Path};
+use thiserror::Error;
+
+/// Design data authored as JSON in `assets/design`, like the effect table or the ingredients.
+pub trait DesignData: DeserializeOwned + Send + Sync + 'static {
+    /// What the data is called in messages, like "effect table".
+    const NAME: &'static str;
+    /// Where the data is authored.
+    const PATH: &'static str;
+
+    /// Problems that deserializing doesn't catch, each with the JSON path to where it was found.
+    fn validate(&self) -> Vec<(String, FieldError)> {
+        Vec::new()
+    }
+}
+
+/// A problem with a value in some design data.
+#[derive(Error, Debug, PartialEq)]
+pub enum FieldError {
+    #[error("{message}")]
+    JsonError { message: String },
+    #[error("{value} isn't between {min} and {max}")]
+    RangeError { value: f32, min: f32, max: f32 },
+    #[error("{value} is already used at {path}")]
+    RepeatedError { value: String, path: String },
+}
+
+/// A problem found in some design data, along with where it was found.
+#[derive(Debug, PartialEq)]
+pub struct FieldDiagnostic {
+    /// JSON path to the offending value, like `$.bands[1].min_degrees`.
+    pub path: String,
+    pub line: Option<usize>,
+    pub error: FieldError,
+}
+
+impl fmt::Display for FieldDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}, {}: {}", line, self.path, self.error),
+            None => write!(f, "{}: {}", self.path, self.error),
+        }
+    }
+}
+
+pub(crate) fn display_diagnostics<D: fmt::Display>(diagnostics: &[D]) -> String {
+    diagnostics
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[derive(Error, Debug)]
+pub enum DesignError {
+    #[error("failed to read design data: {0}")]
+    IoError(#[from] io::Error),
+    #[error("invalid design data:\n{}", display_diagnostics(.0))]
+    InvalidError(Vec<FieldDiagnostic>),
+    #[error("{path}: {source}")]
+    FileError {
+        path: String,
+        source: Box<DesignError>,
+    },
+}
+
+/// Parse design data from JSON, reporting every problem that `validate` finds at once.
+pub fn parse_design<T: DesignData>(source: &str) -> Result<T, DesignError> {
+    let data: T = serde_json::from_str(source).map_err(|e| {
+        DesignError::InvalidError(vec![FieldDiagnostic {
+            path: "$".to_string(),
+            line: Some(e.line()),
+            error: FieldError::JsonError {
+                message: e.to_string(),
+            },
+        }])
+    })?;
+
+    let diagnostics = data
+        .validate()
+        .into_iter()
+        .map(|(path, error)| FieldDiagnostic {
+            line: locate_path(source, &path),
+            path,
+            error,
+        })
+        .collect::<Vec<FieldDiagnostic>>();
+    if diagnostics.is_empty() {
+        Ok(data)
+    } else {
+        Err(DesignError::InvalidError(diagnostics))
+    }
+}
+
+pub fn load_design<T: DesignData, P: AsRef<Path>>(path: P) -> Result<T, DesignError> {
+    fs::read_to_string(&path)
+        .map_err(DesignError::from)
+        .and_then(|source| parse_design(&source))
+        .map_err(|e| DesignError::FileError {
+            path: path.as_ref().display().to_string(),
+            source: Box::new(e),
+        })
+}
+
+/// Inserts the design data at `T::PATH` as a resource, like `insert_reaction_rules` does the rules.
+pub fn insert_design<T: DesignData>(mut commands: Commands) {
+    match load_design::<T, _>(T::PATH) {
+        Ok(data) => commands.insert_resource(data),
+        Err(e) => panic!("Failed to load {}: {}", T::NAME, e),
+    }
+}
+
+/// A step of a JSON path, like the `.heat` or the `[3]` of `$[3].heat`.
+enum PathSegment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+fn parse_path(path: &str) -> Option<Vec<PathSegment<'_>>> {
+    let mut rest = path.strip_prefix('$')?;
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let (index, after) = after.split_once(']')?;
+            segments.push(PathSegment::Index(index.parse().ok()?));
+            rest = after;
+        } else {
+            let after = rest.strip_prefix('.')?;
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            segments.push(PathSegment::Key(&after[..end]));
+            rest = &after[end..];
+        }
+    }
+    Some(segments)
+}
+
+/// Steps through some JSON, keeping count of the lines.
+/// Only meant for JSON that's already known to be valid.
+struct JsonScanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    line: usize,
+}
+
+impl<'a> JsonScanner<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn advance(&mut self) {
+        if self.peek() == Some(b'\n') {
+            self.line += 1;
+        }
+        self.position += 1;
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_ascii_whitespace()) {
+            self.advance();
+        }
+    }
+
+    /// Skip the string starting here, returning what's in it with any escapes left as they are.
+    fn skip_string(&mut self) -> &'a [u8] {
+        self.advance();
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            match c {
+                b'\\' => self.advance(),
+                b'"' => break,
+                _ => (),
+            }
+            self.advance();
+        }
+        let string = &self.bytes[start..self.position];
+        self.advance();
+        string
+    }
+
+    fn skip_value(&mut self) {
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            match c {
+                b'"' => {
+                    self.skip_string();
+                }
+                b']' | b'}' | b',' if depth == 0 => return,
+                b'[' | b'{' => {
+                    depth += 1;
+                    self.advance();
+                }
+                b']' | b'}' => {
+                    depth -= 1;
+                    self.advance();
+                }
+                _ => self.advance(),
+            }
+        }
+    }
+
+    /// Move into the value of `key` in the object here, if there's one.
+    fn enter_key(&mut self, key: &str) -> bool {
+        if self.peek() != Some(b'{') {
+            return false;
+        }
+        self.advance();
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return false;
+            }
+            let found = self.skip_string() == key.as_bytes();
+            self.skip_whitespace();
+            self.advance();
+            self.skip_whitespace();
+            if found {
+                return true;
+            }
+            self.skip_value();
+            self.skip_whitespace();
+            if self.peek() != Some(b',') {
+                return false;
+            }
+            self.advance();
+        }
+    }
+
+    /// Move into the element at `index` in the array here, if there's one.
+    fn enter_index(&mut self, index: usize) -> bool {
+        if self.peek() != Some(b'[') {
+            return false;
+        }
+        self.advance();
+        for i in 0.. {
+            self.skip_whitespace();
+            if self.peek() == Some(b']') {
+                return false;
+            }
+            if i == index {
+                return true;
+            }
+            self.skip_value();
+            self.skip_whitespace();
+            if self.peek() != Some(b',') {
+                return false;
+            }
+            self.advance();
+        }
+        false
+    }
+}
+
+/// Find the line of the value at a JSON path like `$[3].heat` or `$.bands[1].min_degrees` in some JSON.
+/// If the path leads somewhere that isn't there, it's the line of the deepest value on the way.
+pub(crate) fn locate_path(source: &str, path: &str) -> Option<usize> {
+    let mut scanner = JsonScanner {
+        bytes: source.as_bytes(),
+        position: 0,
+        line: 1,
+    };
+    scanner.skip_whitespace();
+    for segment in parse_path(path)? {
+        let (position, line) = (scanner.position, scanner.line);
+        let entered = match segment {
+            PathSegment::Key(key) => scanner.enter_key(key),
+            PathSegment::Index(index) => scanner.enter_index(index),
+        };
+        if !entered {
+            scanner.position = position;
+            scanner.line = line;
+            break;
+        }
+    }
+    Some(scanner.line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::{
+        distillation::DistillationRules, effect::EffectTable, extraction::ExtractionTechniques,
+        fermentation::FermentationRules, ingredient::Ingredients, interaction::EffectInteractions,
+        stirring::StirSettings, temperature::TemperatureSettings,
+    };
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Chances(Vec<f32>);
+
+    impl DesignData for Chances {
+        const NAME: &'static str = "chances";
+        const PATH: &'static str = "chances.json";
+
+        fn validate(&self) -> Vec<(String, FieldError)> {
+            self.0
+                .iter()
+                .enumerate()
+                .filter(|(_, chance)| !(0.0..=1.0).contains(*chance))
+                .map(|(index, chance)| {
+                    (
+                        format!("$[{}]", index),
+                        FieldError::RangeError {
+                            value: *chance,
+                            min: 0.0,
+                            max: 1.0,
+                        },
+                    )
+                })
+                .collect()
+        }
+    }
+
+    fn diagnostics(source: &str) -> Vec<(String, Option<usize>)> {
+        match parse_design::<Chances>(source) {
+            Err(DesignError::InvalidError(diagnostics)) => {
+                diagnostics.into_iter().map(|d| (d.path, d.line)).collect()
+            }
+            _ => panic!("Chances should be invalid"),
+        }
+    }
+
+    #[test]
+    fn test_problems_are_located() {
+        assert_eq!(
+            diagnostics("[\n\t1,\n\t2,\n\t0.5,\n\t-1\n]"),
+            vec![("$[1]".to_string(), Some(3)), ("$[3]".to_string(), Some(5))]
+        );
+        assert_eq!(
+            diagnostics("[\n\t1,\n\t\"0.5\"\n]"),
+            vec![("$".to_string(), Some(3))]
+        );
+    }
+
+    #[test]
+    fn test_locate_path() {
+        let source = r#"{
+    "ambient": 20,
+    "name": "a \"quoted\" [name], {really}",
+    "bands": [
+        {"heat": "Warm", "min_degrees": 40},
+        {
+            "heat": "Boiling",
+            "min_degrees": 100
+        }
+    ]
+}"#;
+        assert_eq!(locate_path(source, "$"), Some(1));
+        assert_eq!(locate_path(source, "$.ambient"), Some(2));
+        assert_eq!(locate_path(source, "$.bands[0].min_degrees"), Some(5));
+        assert_eq!(locate_path(source, "$.bands[1]"), Some(6));
+        assert_eq!(locate_path(source, "$.bands[1].min_degrees"), Some(8));
+        // As far as the path goes, when it leads somewhere that isn't there.
+        assert_eq!(locate_path(source, "$.bands[1].colour"), Some(6));
+        assert_eq!(locate_path(source, "$.bands[2]"), Some(4));
+        assert_eq!(locate_path(source, "bands"), None);
+    }
+
+    #[test]
+    fn test_missing_design_data_is_reported() {
+        assert!(matches!(
+            load_design::<Chances, _>(Chances::PATH),
+            Err(DesignError::FileError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_design_assets_are_valid() {
+        load_design::<EffectTable, _>(EffectTable::PATH).expect("Effect table should be valid");
+        load_design::<EffectInteractions, _>(EffectInteractions::PATH)
+            .expect("Effect interactions should be valid");
+        load_design::<Ingredients, _>(Ingredients::PATH).expect("Ingredients should be valid");
+        load_design::<DistillationRules, _>(DistillationRules::PATH)
+            .expect("Distillation rules should be valid");
+        load_design::<FermentationRules, _>(FermentationRules::PATH)
+            .expect("Fermentation rules should be valid");
+        load_design::<ExtractionTechniques, _>(ExtractionTechniques::PATH)
+            .expect("Extraction techniques should be valid");
+        load_design::<TemperatureSettings, _>(TemperatureSettings::PATH)
+            .expect("Temperature settings should be valid");
+        load_design::<StirSettings, _>(StirSettings::PATH).expect("Stir settings should be valid");
+    }
+}
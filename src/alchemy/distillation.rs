@@ -0,0 +1,167 @@
+use crate::alchemy::{
+    components::*,
+    compound::{Compound, COMPOUND_WEIGHT},
+    design::DesignData,
+    element::Element,
+    resources::ReactionRule,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// What decides whether a compound is carried over by a distillation.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "property")]
+pub enum DistillationCriterion {
+    /// Compounds made of light elements, where the average alton weight of their elements
+    /// is at most `max_mean_weight`.
+    Volatile { max_mean_weight: f32 },
+    /// Compounds with at least `min_count` of `element`.
+    ContainsElement { element: Element, min_count: u32 },
+    /// Compounds with a reaction rule for `heat`, or for any heat.
+    HeatAffinity { heat: Heat },
+}
+
+/// A way of distilling a cauldron, as authored in `assets/design/distillation_rules.json`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DistillationRule {
+    pub name: String,
+    pub criterion: DistillationCriterion,
+}
+
+impl DistillationRule {
+    /// Whether this distillation carries `compound` over into the new container.
+    pub fn separates(&self, compound: &Compound, reaction_rules: &[ReactionRule]) -> bool {
+        match &self.criterion {
+            DistillationCriterion::Volatile { max_mean_weight } => {
+                let element_count = Element::iter()
+                    .map(|element| compound.element_count(element))
+                    .sum::<u32>();
+                COMPOUND_WEIGHT as f32 / element_count as f32 <= *max_mean_weight
+            }
+            DistillationCriterion::ContainsElement { element, min_count } => {
+                compound.element_count(*element) >= *min_count
+            }
+            DistillationCriterion::HeatAffinity { heat } => reaction_rules
+                .iter()
+                .any(|rule| rule.covers(compound) && rule.heat.map_or(true, |h| h == *heat)),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct DistillationRules(pub Vec<DistillationRule>);
+
+impl DesignData for DistillationRules {
+    const NAME: &'static str = "distillation rules";
+    const PATH: &'static str = "assets/design/distillation_rules.json";
+}
+
+impl DistillationRules {
+    pub fn get(&self, name: &str) -> Option<&DistillationRule> {
+        self.0.iter().find(|rule| rule.name == name)
+    }
+}
+
+/// Send this to distill a cauldron with the distillation rule named `rule`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Distill {
+    pub cauldron: Entity,
+    pub rule: String,
+}
+
+/// Sent once a cauldron has been distilled, with the `Flask` that the distillate was moved to.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Distilled {
+    pub cauldron: Entity,
+    pub flask: Entity,
+}
+
+pub fn distilling(
+    mut commands: Commands,
+    mut distill_events: EventReader<Distill>,
+    mut distilled_events: EventWriter<Distilled>,
+    mut compound_query: Query<(&Compound, &mut ContainedIn)>,
+    distillation_rules: Res<DistillationRules>,
+    reaction_rules: Res<Vec<ReactionRule>>,
+) {
+    for Distill { cauldron, rule } in distill_events.iter() {
+        let rule = match distillation_rules.get(rule) {
+            Some(rule) => rule,
+            None => {
+                warn!("Tried to distill with unknown rule {:?}", rule);
+                continue;
+            }
+        };
+
+        let flask = commands.spawn().insert(Flask).id();
+        for (compound, mut contained_in) in compound_query.iter_mut() {
+            if contained_in.0 == *cauldron && rule.separates(compound, &reaction_rules) {
+                contained_in.0 = flask;
+            }
+        }
+
+        distilled_events.send(Distilled {
+            cauldron: *cauldron,
+            flask,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::compound::CompoundError;
+
+    fn rule(criterion: &str) -> DistillationRule {
+        serde_json::from_str(&format!(
+            r#"{{"name": "Test", "criterion": {}}}"#,
+            criterion
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_volatile() -> Result<(), CompoundError> {
+        let volatile = rule(r#"{"property": "Volatile", "max_mean_weight": 2.0}"#);
+        assert!(volatile.separates(&"7A".parse()?, &[]));
+        assert!(volatile.separates(&"A3B".parse()?, &[]));
+        assert!(!volatile.separates(&"BE".parse()?, &[]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_contains_element() -> Result<(), CompoundError> {
+        let contains_e = rule(r#"{"property": "ContainsElement", "element": "E", "min_count": 1}"#);
+        assert!(contains_e.separates(&"BE".parse()?, &[]));
+        assert!(!contains_e.separates(&"7A".parse()?, &[]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_heat_affinity() -> Result<(), CompoundError> {
+        let boiling = rule(r#"{"property": "HeatAffinity", "heat": "Boiling"}"#);
+        let reaction_rules = vec![
+            ReactionRule {
+                compound: "BE | 2AE".parse().unwrap(),
+                heat: Some(Heat::Boiling),
+                ..Default::default()
+            },
+            ReactionRule {
+                compound: "A3B".parse().unwrap(),
+                heat: None,
+                ..Default::default()
+            },
+            ReactionRule {
+                compound: "3AD".parse().unwrap(),
+                heat: Some(Heat::Simmering),
+                ..Default::default()
+            },
+        ];
+        assert!(boiling.separates(&"BE".parse()?, &reaction_rules));
+        assert!(boiling.separates(&"2AE".parse()?, &reaction_rules));
+        assert!(boiling.separates(&"A3B".parse()?, &reaction_rules));
+        assert!(!boiling.separates(&"3AD".parse()?, &reaction_rules));
+        assert!(!boiling.separates(&"7A".parse()?, &reaction_rules));
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use crate::alchemy::{compound::Compound, design::DesignData, element::Element};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::collections::{BTreeMap, HashMap};
+
+/// Something that happens when a compound is consumed, like "Levity" or "Sleep".
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Effect {
+    pub kind: String,
+    /// Negative magnitudes work against other effects of the same kind.
+    pub magnitude: f32,
+}
+
+/// A collection of effects, where effects of the same kind are combined by adding magnitudes.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Effects(pub BTreeMap<String, f32>);
+
+impl Effects {
+    pub fn add(&mut self, effect: &Effect) {
+        *self.0.entry(effect.kind.clone()).or_insert(0.0) += effect.magnitude;
+    }
+
+    pub fn extend(&mut self, other: &Effects) {
+        for (kind, magnitude) in &other.0 {
+            *self.0.entry(kind.clone()).or_insert(0.0) += magnitude;
+        }
+    }
+
+    pub fn scaled(&self, factor: f32) -> Effects {
+        Effects(
+            self.0
+                .iter()
+                .map(|(kind, magnitude)| (kind.clone(), magnitude * factor))
+                .collect(),
+        )
+    }
+
+    pub fn magnitude(&self, kind: &str) -> f32 {
+        self.0.get(kind).copied().unwrap_or(0.0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Effect> + '_ {
+        self.0.iter().map(|(kind, magnitude)| Effect {
+            kind: kind.clone(),
+            magnitude: *magnitude,
+        })
+    }
+}
+
+/// Design data deciding the effects of every compound, as authored in
+/// `assets/design/effects.json`.
+///
+/// A compound's effects are the effects of each of its elements, multiplied by how many of that
+/// element it has. Unless the compound has its own entry, which replaces those entirely.
+#[serde_as]
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct EffectTable {
+    #[serde(default)]
+    pub elements: HashMap<Element, Vec<Effect>>,
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    #[serde(default)]
+    pub compounds: HashMap<Compound, Vec<Effect>>,
+}
+
+impl DesignData for EffectTable {
+    const NAME: &'static str = "effect table";
+    const PATH: &'static str = "assets/design/effects.json";
+}
+
+impl EffectTable {
+    pub fn compound_effects(&self, compound: &Compound) -> Effects {
+        let mut effects = Effects::default();
+        match self.compounds.get(compound) {
+            Some(compound_effects) => compound_effects.iter().for_each(|e| effects.add(e)),
+            None => {
+                for element in Element::iter() {
+                    let count = compound.element_count(element);
+                    for effect in self.elements.get(&element).into_iter().flatten() {
+                        effects.add(&Effect {
+                            kind: effect.kind.clone(),
+                            magnitude: effect.magnitude * count as f32,
+                        });
+                    }
+                }
+            }
+        }
+        effects
+    }
+
+    /// The combined effects of every compound in a population.
+    /// These add up, so twice the compounds give effects of twice the magnitude.
+    pub fn population_effects<'a, I>(&self, compounds: I) -> Effects
+    where
+        I: IntoIterator<Item = &'a Compound>,
+    {
+        let mut cached_effects: HashMap<&Compound, Effects> = HashMap::new();
+        let mut effects = Effects::default();
+        for compound in compounds {
+            effects.extend(
+                cached_effects
+                    .entry(compound)
+                    .or_insert_with(|| self.compound_effects(compound)),
+            );
+        }
+        effects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::{compound::CompoundError, design::parse_design};
+
+    fn effect_table() -> EffectTable {
+        parse_design(
+            r#"{
+                "elements": {
+                    "A": [{"kind": "Levity", "magnitude": 1.0}],
+                    "E": [{"kind": "Clarity", "magnitude": 2.0}, {"kind": "Levity", "magnitude": -1.0}]
+                },
+                "compounds": {
+                    "7A": [{"kind": "Flight", "magnitude": 1.0}]
+                }
+            }"#,
+        )
+        .expect("Effect table should be valid")
+    }
+
+    #[test]
+    fn test_compound_effects_from_elements() -> Result<(), CompoundError> {
+        let effects = effect_table().compound_effects(&"2AE".parse()?);
+        assert_eq!(effects.magnitude("Levity"), 1.0);
+        assert_eq!(effects.magnitude("Clarity"), 2.0);
+        assert_eq!(effects.magnitude("Flight"), 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compound_effects_override_elements() -> Result<(), CompoundError> {
+        let effects = effect_table().compound_effects(&"7A".parse()?);
+        assert_eq!(effects.magnitude("Flight"), 1.0);
+        assert_eq!(effects.magnitude("Levity"), 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_population_effects_add_up() -> Result<(), CompoundError> {
+        let population: Vec<Compound> = vec!["2AE".parse()?, "2AE".parse()?, "7A".parse()?];
+        let effects = effect_table().population_effects(&population);
+        assert_eq!(effects.magnitude("Levity"), 2.0);
+        assert_eq!(effects.magnitude("Clarity"), 4.0);
+        assert_eq!(effects.magnitude("Flight"), 1.0);
+        Ok(())
+    }
+}
@@ -1,59 +1,269 @@
 use crate::alchemy::AltonWeighable;
-use nom::{character::complete, IResult};
-use serde::{Deserialize, Serialize};
-use std::cmp;
-use strum::{Display, EnumIter, EnumString};
+use nom::{bytes, character, combinator, sequence, IResult};
+use once_cell::sync::OnceCell;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{cmp, collections::HashMap, fmt, fs, io, path::Path};
+use thiserror::Error;
+
+pub const ELEMENT_TABLE_PATH: &str = "assets/design/elements.json";
+
+/// The table at `ELEMENT_TABLE_PATH`, built in so it doesn't depend on the working directory.
+const DEFAULT_ELEMENT_TABLE: &str = include_str!("../../assets/design/elements.json");
+
+static ELEMENT_TABLE: OnceCell<ElementTable> = OnceCell::new();
+
+#[derive(Error, Debug)]
+pub enum ElementTableError {
+    #[error("failed to read element table: {0}")]
+    IoError(#[from] io::Error),
+    #[error("failed to deserialize element table: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("element table contains no elements")]
+    EmptyError,
+    #[error("invalid element symbol: {symbol:?}")]
+    SymbolError { symbol: String },
+    #[error("duplicate element symbol: {symbol}")]
+    DuplicateError { symbol: String },
+    #[error("element {symbol} has an alton weight of 0")]
+    WeightError { symbol: String },
+    #[error("element table has already been initialized")]
+    InitializedError,
+}
+
+/// Design data for a single element, as authored in `assets/design/elements.json`.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ElementDefinition {
+    /// An uppercase letter optionally followed by lowercase letters, like "A" or "Ae".
+    pub symbol: String,
+    pub name: String,
+    pub weight: u32,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Registry of every element in the game, sorted by alton weight.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ElementTable {
+    definitions: Vec<ElementDefinition>,
+}
+
+impl ElementTable {
+    pub fn new(mut definitions: Vec<ElementDefinition>) -> Result<ElementTable, ElementTableError> {
+        if definitions.is_empty() {
+            return Err(ElementTableError::EmptyError);
+        }
+
+        for (i, definition) in definitions.iter().enumerate() {
+            let mut chars = definition.symbol.chars();
+            let valid_symbol = matches!(chars.next(), Some(c) if c.is_ascii_uppercase())
+                && chars.all(|c| c.is_ascii_lowercase());
+            if !valid_symbol {
+                return Err(ElementTableError::SymbolError {
+                    symbol: definition.symbol.clone(),
+                });
+            }
+            if definition.weight == 0 {
+                return Err(ElementTableError::WeightError {
+                    symbol: definition.symbol.clone(),
+                });
+            }
+            if definitions[..i]
+                .iter()
+                .any(|d| d.symbol == definition.symbol)
+            {
+                return Err(ElementTableError::DuplicateError {
+                    symbol: definition.symbol.clone(),
+                });
+            }
+        }
+
+        // Stable, so elements of equal weight keep the order they were authored in.
+        definitions.sort_by_key(|d| d.weight);
+
+        Ok(ElementTable { definitions })
+    }
+
+    pub fn from_json(data: &str) -> Result<ElementTable, ElementTableError> {
+        ElementTable::new(serde_json::from_str(data)?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ElementTable, ElementTableError> {
+        ElementTable::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn definitions(&self) -> &[ElementDefinition] {
+        &self.definitions
+    }
+}
+
+/// Install the global element table.
+/// Has to be called before any `Element` is used, otherwise the built-in table will already
+/// have been loaded.
+pub fn init_element_table(table: ElementTable) -> Result<(), ElementTableError> {
+    ELEMENT_TABLE
+        .set(table)
+        .map_err(|_| ElementTableError::InitializedError)
+}
+
+/// The global element table, the built-in copy of `ELEMENT_TABLE_PATH` unless another
+/// was installed with `init_element_table`.
+pub fn element_table() -> &'static ElementTable {
+    ELEMENT_TABLE.get_or_init(|| {
+        ElementTable::from_json(DEFAULT_ELEMENT_TABLE).expect("Failed to load element table")
+    })
+}
 
 /// The most basic alchemical object.
-#[derive(
-    Copy,
-    Clone,
-    Eq,
-    PartialEq,
-    PartialOrd,
-    Debug,
-    Hash,
-    Serialize,
-    Deserialize,
-    Display,
-    EnumIter,
-    EnumString,
-)]
-pub enum Element {
-    A,
-    B,
-    C,
-    D,
-    E,
+/// Elements are defined in the global `ElementTable`, this is just a handle into it.
+/// Since the table is sorted by weight, so are elements.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub struct Element(usize);
+
+impl Element {
+    pub fn from_symbol(symbol: &str) -> Option<Element> {
+        element_table()
+            .definitions
+            .iter()
+            .position(|d| d.symbol == symbol)
+            .map(Element)
+    }
+
+    /// Every element in the table, lightest first.
+    pub fn iter() -> impl Iterator<Item = Element> {
+        (0..element_table().definitions.len()).map(Element)
+    }
+
+    pub fn definition(&self) -> &'static ElementDefinition {
+        &element_table().definitions[self.0]
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        &self.definition().symbol
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+impl Serialize for Element {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.symbol())
+    }
+}
+
+impl<'de> Deserialize<'de> for Element {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Element, D::Error> {
+        let symbol = String::deserialize(deserializer)?;
+        Element::from_symbol(&symbol)
+            .ok_or_else(|| de::Error::custom(format!("unknown element symbol: {}", symbol)))
+    }
 }
 
 pub fn element_parser_maker(element: Element) -> impl Fn(&str) -> IResult<&str, Element> {
     move |input: &str| {
-        let (input, _) = complete::char(
-            element
-                .to_string()
-                .chars()
-                .next()
-                .expect("Element::to_string() should contain at least one character"),
+        // Refuse to match "A" at the start of "Ae", for example.
+        let (input, _) = sequence::terminated(
+            bytes::complete::tag(element.symbol()),
+            combinator::not(character::complete::satisfy(|c| c.is_ascii_lowercase())),
         )(input)?;
         Ok((input, element))
     }
 }
 
+/// Parses any element, ignoring case.
+/// Symbols written in the right case are preferred, so "AE" is always A followed by E.
+/// Otherwise longer symbols are tried first, so "ae" is read as "Ae" if there's such an element.
+pub fn lenient_element_parser(input: &str) -> IResult<&str, Element> {
+    if let Some(parsed) = Element::iter().find_map(|e| element_parser_maker(e)(input).ok()) {
+        return Ok(parsed);
+    }
+
+    let mut elements = Element::iter().collect::<Vec<Element>>();
+    elements.sort_by_key(|e| cmp::Reverse(e.symbol().len()));
+    elements
+        .into_iter()
+        .find_map(|element| {
+            let symbol = element.symbol();
+            input
+                .get(..symbol.len())
+                .filter(|prefix| prefix.eq_ignore_ascii_case(symbol))
+                .map(|_| (&input[symbol.len()..], element))
+        })
+        .ok_or_else(|| nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag)))
+}
+
 impl AltonWeighable for Element {
     fn weight(&self) -> u32 {
-        match self {
-            Element::A => 1,
-            Element::B => 2,
-            Element::C => 3,
-            Element::D => 4,
-            Element::E => 5,
-        }
+        self.definition().weight
     }
 }
 
-impl Ord for Element {
-    fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.weight().cmp(&other.weight())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(symbol: &str, weight: u32) -> ElementDefinition {
+        ElementDefinition {
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            weight,
+            color: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_element_table_sorted_by_weight() -> Result<(), ElementTableError> {
+        let table = ElementTable::new(vec![
+            definition("C", 3),
+            definition("A", 1),
+            definition("Ae", 1),
+        ])?;
+        assert_eq!(
+            table
+                .definitions()
+                .iter()
+                .map(|d| d.symbol.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["A", "Ae", "C"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lenient_element_parser() {
+        let (a, e) = (Element::from_symbol("A"), Element::from_symbol("E"));
+        assert_eq!(lenient_element_parser("AE").map(|(_, e)| Some(e)), Ok(a));
+        assert_eq!(lenient_element_parser("e").map(|(_, e)| Some(e)), Ok(e));
+        assert!(lenient_element_parser("x").is_err());
+    }
+
+    #[test]
+    fn test_element_table_validation() {
+        assert!(matches!(
+            ElementTable::new(vec![]),
+            Err(ElementTableError::EmptyError)
+        ));
+        assert!(matches!(
+            ElementTable::new(vec![definition("a", 1)]),
+            Err(ElementTableError::SymbolError { .. })
+        ));
+        assert!(matches!(
+            ElementTable::new(vec![definition("A2", 1)]),
+            Err(ElementTableError::SymbolError { .. })
+        ));
+        assert!(matches!(
+            ElementTable::new(vec![definition("A", 1), definition("A", 2)]),
+            Err(ElementTableError::DuplicateError { .. })
+        ));
+        assert!(matches!(
+            ElementTable::new(vec![definition("A", 0)]),
+            Err(ElementTableError::WeightError { .. })
+        ));
     }
 }
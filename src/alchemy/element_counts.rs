@@ -1,5 +1,9 @@
 use crate::alchemy::{element::*, AltonWeighable};
-use nom::{character::complete, combinator, IResult};
+use nom::{
+    branch, bytes,
+    character::complete::{self, multispace0},
+    combinator, multi, sequence, IResult,
+};
 use std::collections::HashMap;
 
 pub type ElementCounts = HashMap<Element, u32>;
@@ -18,19 +22,50 @@ pub fn element_count_parser(element: Element) -> impl Fn(&str) -> IResult<&str,
     }
 }
 
+/// Parses element counts in element table order, like "3A2B" or "BE".
 pub fn element_counts_parser(input: &str) -> IResult<&str, ElementCounts> {
-    let (input, opt_a) = combinator::opt(element_count_parser(Element::A))(input)?;
-    let (input, opt_b) = combinator::opt(element_count_parser(Element::B))(input)?;
-    let (input, opt_c) = combinator::opt(element_count_parser(Element::C))(input)?;
-    let (input, opt_d) = combinator::opt(element_count_parser(Element::D))(input)?;
-    let (input, opt_e) = combinator::opt(element_count_parser(Element::E))(input)?;
-    Ok((
-        input,
-        vec![opt_a, opt_b, opt_c, opt_d, opt_e]
-            .into_iter()
-            .flatten()
-            .collect(),
-    ))
+    let mut element_counts = ElementCounts::new();
+    let mut input = input;
+    for element in Element::iter() {
+        let (remaining, opt_count) = combinator::opt(element_count_parser(element))(input)?;
+        element_counts.extend(opt_count);
+        input = remaining;
+    }
+    Ok((input, element_counts))
+}
+
+/// Parses element counts in any order, like "D3A", "AAB" or " 2a b ".
+/// Repeated elements add up, and whitespace and case are ignored.
+/// Parenthesized groups can be multiplied, so "2(AB)A" is the same as "3A2B".
+pub fn lenient_element_counts_parser(input: &str) -> IResult<&str, ElementCounts> {
+    let (input, terms) = multi::many1(sequence::delimited(
+        multispace0,
+        sequence::pair(
+            combinator::opt(complete::u32),
+            sequence::preceded(
+                multispace0,
+                branch::alt((
+                    combinator::map(lenient_element_parser, |element| {
+                        ElementCounts::from([(element, 1)])
+                    }),
+                    sequence::delimited(
+                        bytes::complete::tag("("),
+                        lenient_element_counts_parser,
+                        bytes::complete::tag(")"),
+                    ),
+                )),
+            ),
+        ),
+        multispace0,
+    ))(input)?;
+
+    let mut element_counts = ElementCounts::new();
+    for (count, term_element_counts) in terms {
+        for (element, term_count) in term_element_counts {
+            *element_counts.entry(element).or_insert(0) += count.unwrap_or(1) * term_count;
+        }
+    }
+    Ok((input, element_counts))
 }
 
 pub fn add_element_counts(
@@ -0,0 +1,278 @@
+use crate::alchemy::{
+    components::*,
+    compound::Compound,
+    design::{DesignData, FieldError},
+    ingredient::Ingredients,
+    pattern::CompoundPattern,
+    resources::BrewingRng,
+};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+/// How quickly some compounds leave an ingredient.
+#[serde_as]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ExtractionRate {
+    /// The compounds this rate covers, like "7A" or "contains E"
+    #[serde_as(as = "DisplayFromStr")]
+    pub compound: CompoundPattern,
+    /// The chance of each of these compounds being released on any given tick.
+    pub rate: f32,
+    /// How many ticks the ingredient has to have steeped for before these compounds are released.
+    #[serde(default)]
+    pub after_ticks: u32,
+}
+
+/// A way of drawing compounds out of an ingredient into a solvent, like an infusion,
+/// as authored in `assets/design/extraction_techniques.json`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ExtractionTechnique {
+    pub name: String,
+    /// The heat of the solvent that this technique is used under.
+    /// Setting to None means this is the technique for a solvent with no heat
+    pub heat: Option<Heat>,
+    pub rates: Vec<ExtractionRate>,
+}
+
+impl ExtractionTechnique {
+    /// The chance of `compound` being released after steeping for `ticks` ticks.
+    /// The first rate covering the compound that has started decides.
+    pub fn rate(&self, compound: &Compound, ticks: u32) -> f32 {
+        self.rates
+            .iter()
+            .find(|rate| ticks >= rate.after_ticks && rate.compound.matches(compound))
+            .map_or(0.0, |rate| rate.rate)
+    }
+
+    /// Whether `compound` is released from an ingredient that has steeped for `ticks` ticks.
+    pub fn releases<R: Rng + ?Sized>(&self, compound: &Compound, ticks: u32, rng: &mut R) -> bool {
+        rng.gen::<f32>() < self.rate(compound, ticks)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct ExtractionTechniques(pub Vec<ExtractionTechnique>);
+
+impl DesignData for ExtractionTechniques {
+    const NAME: &'static str = "extraction techniques";
+    const PATH: &'static str = "assets/design/extraction_techniques.json";
+
+    fn validate(&self) -> Vec<(String, FieldError)> {
+        let mut errors = Vec::new();
+        for (index, technique) in self.0.iter().enumerate() {
+            for (rate_index, rate) in technique.rates.iter().enumerate() {
+                if !(0.0..=1.0).contains(&rate.rate) {
+                    errors.push((
+                        format!("$[{}].rates[{}].rate", index, rate_index),
+                        FieldError::RangeError {
+                            value: rate.rate,
+                            min: 0.0,
+                            max: 1.0,
+                        },
+                    ));
+                }
+            }
+            if let Some(other_index) = self.0[..index]
+                .iter()
+                .position(|other| other.heat == technique.heat)
+            {
+                errors.push((
+                    format!("$[{}].heat", index),
+                    FieldError::RepeatedError {
+                        value: technique
+                            .heat
+                            .map_or("no heat".to_string(), |heat| heat.to_string()),
+                        path: format!("$[{}]", other_index),
+                    },
+                ));
+            }
+        }
+        errors
+    }
+}
+
+impl ExtractionTechniques {
+    /// The technique used for a solvent under `heat`, if any.
+    pub fn get(&self, heat: Option<Heat>) -> Option<&ExtractionTechnique> {
+        self.0.iter().find(|technique| technique.heat == heat)
+    }
+}
+
+/// An ingredient steeping in a solvent, holding the compounds it hasn't released yet.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Steeping {
+    pub solvent: Entity,
+    /// How many ticks the ingredient has steeped for.
+    pub ticks: u32,
+}
+
+/// Send this to steep an ingredient, by name, in a solvent like a cauldron.
+/// Unlike `AddIngredient`, its compounds are released into the solvent over time,
+/// depending on the solvent's heat.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct SteepIngredient {
+    pub ingredient: String,
+    pub solvent: Entity,
+}
+
+pub fn steeping_ingredients(
+    mut commands: Commands,
+    mut steep_events: EventReader<SteepIngredient>,
+    ingredients: Res<Ingredients>,
+    mut brewing_rng: ResMut<BrewingRng>,
+) {
+    for SteepIngredient {
+        ingredient,
+        solvent,
+    } in steep_events.iter()
+    {
+        match ingredients.get(ingredient) {
+            Some(ingredient) => {
+                let steeping = commands
+                    .spawn()
+                    .insert(Steeping {
+                        solvent: *solvent,
+                        ticks: 0,
+                    })
+                    .id();
+                for compound in ingredient.roll(&mut brewing_rng.0) {
+                    commands
+                        .spawn()
+                        .insert(compound)
+                        .insert(ContainedIn(steeping));
+                }
+            }
+            None => warn!("Tried to steep unknown ingredient {:?}", ingredient),
+        }
+    }
+}
+
+/// Every steeping ingredient releases some of its compounds into its solvent, run on the brewing tick.
+/// Spent ingredients, with nothing left to release, are removed.
+pub fn extracting(
+    mut commands: Commands,
+    mut steeping_query: Query<(Entity, &mut Steeping)>,
+    mut compound_query: Query<(&Compound, &mut ContainedIn)>,
+    heat_query: Query<Option<&Heat>>,
+    extraction_techniques: Res<ExtractionTechniques>,
+    mut brewing_rng: ResMut<BrewingRng>,
+) {
+    for (entity, mut steeping) in steeping_query.iter_mut() {
+        let heat = heat_query.get(steeping.solvent).ok().flatten().copied();
+        let technique = extraction_techniques.get(heat);
+
+        let mut spent = true;
+        for (compound, mut contained_in) in compound_query.iter_mut() {
+            if contained_in.0 == entity {
+                match technique {
+                    Some(technique)
+                        if technique.releases(compound, steeping.ticks, &mut brewing_rng.0) =>
+                    {
+                        contained_in.0 = steeping.solvent
+                    }
+                    _ => spent = false,
+                }
+            }
+        }
+
+        if spent {
+            commands.entity(entity).despawn();
+        } else {
+            steeping.ticks += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::design::{parse_design, DesignError};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const TECHNIQUES: &str = r#"[
+        {"name": "Maceration", "heat": null, "rates": [{"compound": "7A", "rate": 0.5}]},
+        {
+            "name": "Decoction",
+            "heat": "Boiling",
+            "rates": [
+                {"compound": "contains E", "rate": 1, "after_ticks": 5},
+                {"compound": "*", "rate": 0.5}
+            ]
+        }
+    ]"#;
+
+    fn extract(technique: &ExtractionTechnique, ticks: u32) -> Vec<Compound> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut remaining: Vec<Compound> = ["7A", "BE", "A3B"]
+            .iter()
+            .cycle()
+            .take(30)
+            .map(|compound| compound.parse().unwrap())
+            .collect();
+        let mut extract = Vec::new();
+        for tick in 0..ticks {
+            let (released, kept) = remaining
+                .into_iter()
+                .partition(|compound| technique.releases(compound, tick, &mut rng));
+            extract.extend::<Vec<Compound>>(released);
+            remaining = kept;
+        }
+        extract
+    }
+
+    #[test]
+    fn test_techniques_by_heat() {
+        let techniques = parse_design::<ExtractionTechniques>(TECHNIQUES).unwrap();
+        let maceration = techniques.get(None).unwrap();
+        let decoction = techniques.get(Some(Heat::Boiling)).unwrap();
+        assert!(techniques.get(Some(Heat::Simmering)).is_none());
+
+        let (seven_a, be): (Compound, Compound) = ("7A".parse().unwrap(), "BE".parse().unwrap());
+        assert!(extract(maceration, 20).iter().all(|c| *c == seven_a));
+        assert_eq!(extract(maceration, 20).len(), 10);
+        assert_eq!(extract(decoction, 20).len(), 30);
+
+        assert_eq!(decoction.rate(&be, 4), 0.5);
+        assert_eq!(decoction.rate(&be, 5), 1.0);
+    }
+
+    #[test]
+    fn test_invalid_techniques() {
+        let techniques = parse_design::<ExtractionTechniques>(
+            r#"[
+                {"name": "Infusion", "heat": null, "rates": [{"compound": "*", "rate": 2}]},
+                {"name": "Maceration", "heat": null, "rates": []}
+            ]"#,
+        );
+        match techniques {
+            Err(DesignError::InvalidError(diagnostics)) => assert_eq!(
+                diagnostics
+                    .into_iter()
+                    .map(|d| (d.path, d.line, d.error))
+                    .collect::<Vec<(String, Option<usize>, FieldError)>>(),
+                vec![
+                    (
+                        "$[0].rates[0].rate".to_string(),
+                        Some(2),
+                        FieldError::RangeError {
+                            value: 2.0,
+                            min: 0.0,
+                            max: 1.0,
+                        }
+                    ),
+                    (
+                        "$[1].heat".to_string(),
+                        Some(3),
+                        FieldError::RepeatedError {
+                            value: "no heat".to_string(),
+                            path: "$[0]".to_string()
+                        }
+                    ),
+                ]
+            ),
+            _ => panic!("Extraction techniques should be invalid"),
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use crate::alchemy::{
+    components::*,
+    compound::{Compound, COMPOUND_WEIGHT},
+    design::{DesignData, FieldError},
+    pattern::CompoundPattern,
+    reaction_table::ReactionTable,
+    resources::BrewingWeighting,
+    weighting::OutcomeWeighting,
+};
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+/// How many seconds of play make up a day of fermentation.
+pub const FERMENTATION_DAY: f64 = 60.0;
+
+/// Compounds that ferment once they've aged for long enough,
+/// as authored in `assets/design/fermentation_rules.json`.
+#[serde_as]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FermentationRule {
+    /// The compounds this rule covers, like "7A" or "contains E"
+    #[serde_as(as = "DisplayFromStr")]
+    pub compound: CompoundPattern,
+    /// How many days the fermenter has to have aged before these compounds start fermenting.
+    #[serde(default)]
+    pub after_days: u32,
+    /// The day these compounds stop fermenting. Setting to None means they never stop
+    #[serde(default)]
+    pub until_days: Option<u32>,
+    /// The chance of each of these compounds reacting on any given day.
+    pub rate: f32,
+}
+
+impl FermentationRule {
+    /// Whether this rule ferments its compounds on the day after `age` days.
+    pub fn is_active(&self, age: u32) -> bool {
+        age >= self.after_days && self.until_days.map_or(true, |until| age < until)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct FermentationRules(pub Vec<FermentationRule>);
+
+impl DesignData for FermentationRules {
+    const NAME: &'static str = "fermentation rules";
+    const PATH: &'static str = "assets/design/fermentation_rules.json";
+
+    fn validate(&self) -> Vec<(String, FieldError)> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| !(0.0..=1.0).contains(&rule.rate))
+            .map(|(index, rule)| {
+                (
+                    format!("$[{}].rate", index),
+                    FieldError::RangeError {
+                        value: rule.rate,
+                        min: 0.0,
+                        max: 1.0,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+/// The seed for the rng of `fermenter`'s next day.
+/// The seed and age are mixed together with splitmix64 rather than added,
+/// so that fermenters with nearby seeds don't share days.
+/// This has to stay the same across releases, so that saved fermenters keep fermenting the same way.
+fn day_seed(fermenter: &Fermenter) -> u64 {
+    let mut z = (fermenter.seed ^ (fermenter.age as u64).rotate_left(32))
+        .wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl FermentationRules {
+    /// The chance of `compound` reacting on the day after `age` days.
+    /// The first active rule covering the compound decides.
+    pub fn rate(&self, compound: &Compound, age: u32) -> f32 {
+        self.0
+            .iter()
+            .find(|rule| rule.is_active(age) && rule.compound.matches(compound))
+            .map_or(0.0, |rule| rule.rate)
+    }
+
+    /// Ferment `compounds` for a day in `fermenter`, aging it by a day.
+    /// Compounds that react on the same day collide with each other in order, like in a cauldron.
+    pub fn ferment_day<O>(
+        &self,
+        fermenter: &mut Fermenter,
+        compounds: &mut [Compound],
+        reaction_table: &mut ReactionTable<COMPOUND_WEIGHT>,
+        weighting: &O,
+    ) where
+        O: OutcomeWeighting<COMPOUND_WEIGHT> + ?Sized,
+    {
+        let mut rng = StdRng::seed_from_u64(day_seed(fermenter));
+        let colliding = (0..compounds.len())
+            .filter(|i| rng.gen::<f32>() < self.rate(&compounds[*i], fermenter.age))
+            .collect::<Vec<usize>>();
+
+        for pair in colliding.chunks_exact(2) {
+            let (left, right) = compounds.split_at_mut(pair[1]);
+            reaction_table.react(&mut left[pair[0]], &mut right[0], weighting, &mut rng);
+        }
+        fermenter.age += 1;
+    }
+
+    /// Ferment `compounds` for `days` days, the same as fermenting them a day at a time.
+    pub fn ferment<O>(
+        &self,
+        fermenter: &mut Fermenter,
+        compounds: &mut [Compound],
+        days: u32,
+        reaction_table: &mut ReactionTable<COMPOUND_WEIGHT>,
+        weighting: &O,
+    ) where
+        O: OutcomeWeighting<COMPOUND_WEIGHT> + ?Sized,
+    {
+        for _ in 0..days {
+            self.ferment_day(fermenter, compounds, reaction_table, weighting);
+        }
+    }
+}
+
+/// Send this to skip a fermenter ahead by some number of days, like when the player sleeps.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct FastForwardFermenter {
+    pub fermenter: Entity,
+    pub days: u32,
+}
+
+/// Ferments the compounds in the `fermenter` entity for `days` days.
+/// The compounds are fermented in ascending order rather than query order,
+/// which can change as entities move between archetypes,
+/// so that the same fermenter with the same contents always ferments the same way.
+fn ferment_contents(
+    fermenter: Entity,
+    fermenter_component: &mut Fermenter,
+    days: u32,
+    compound_query: &mut Query<(&mut Compound, &ContainedIn)>,
+    fermentation_rules: &FermentationRules,
+    reaction_table: &mut ReactionTable<COMPOUND_WEIGHT>,
+    brewing_weighting: &BrewingWeighting,
+) {
+    let mut contents = compound_query
+        .iter_mut()
+        .filter(|(_, contained_in)| contained_in.0 == fermenter)
+        .map(|(compound, _)| compound)
+        .collect::<Vec<Mut<Compound>>>();
+    contents.sort_by_key(|compound| (**compound).clone());
+    let mut compounds = contents
+        .iter()
+        .map(|compound| (**compound).clone())
+        .collect::<Vec<Compound>>();
+
+    fermentation_rules.ferment(
+        fermenter_component,
+        &mut compounds,
+        days,
+        reaction_table,
+        brewing_weighting.0.as_ref(),
+    );
+
+    for (content, compound) in contents.iter_mut().zip(compounds) {
+        if **content != compound {
+            **content = compound;
+        }
+    }
+}
+
+/// Every fermenter ages by a day, run once every `FERMENTATION_DAY`.
+pub fn fermenting(
+    mut fermenter_query: Query<(Entity, &mut Fermenter)>,
+    mut compound_query: Query<(&mut Compound, &ContainedIn)>,
+    fermentation_rules: Res<FermentationRules>,
+    mut reaction_table: ResMut<ReactionTable<COMPOUND_WEIGHT>>,
+    brewing_weighting: Res<BrewingWeighting>,
+) {
+    for (entity, mut fermenter) in fermenter_query.iter_mut() {
+        ferment_contents(
+            entity,
+            &mut fermenter,
+            1,
+            &mut compound_query,
+            &fermentation_rules,
+            &mut reaction_table,
+            &brewing_weighting,
+        );
+    }
+}
+
+pub fn fast_forwarding_fermenters(
+    mut fast_forward_events: EventReader<FastForwardFermenter>,
+    mut fermenter_query: Query<&mut Fermenter>,
+    mut compound_query: Query<(&mut Compound, &ContainedIn)>,
+    fermentation_rules: Res<FermentationRules>,
+    mut reaction_table: ResMut<ReactionTable<COMPOUND_WEIGHT>>,
+    brewing_weighting: Res<BrewingWeighting>,
+) {
+    for FastForwardFermenter { fermenter, days } in fast_forward_events.iter() {
+        match fermenter_query.get_mut(*fermenter) {
+            Ok(mut fermenter_component) => ferment_contents(
+                *fermenter,
+                &mut fermenter_component,
+                *days,
+                &mut compound_query,
+                &fermentation_rules,
+                &mut reaction_table,
+                &brewing_weighting,
+            ),
+            Err(_) => warn!(
+                "Tried to fast forward {:?}, which isn't a fermenter",
+                fermenter
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::{
+        design::{parse_design, DesignError},
+        weighting::UniformWeighting,
+    };
+
+    fn compounds() -> Vec<Compound> {
+        ["7A", "BE", "A3B", "2AE"]
+            .iter()
+            .cycle()
+            .take(40)
+            .map(|compound| compound.parse().unwrap())
+            .collect()
+    }
+
+    fn rules(data: &str) -> FermentationRules {
+        parse_design(data).unwrap()
+    }
+
+    #[test]
+    fn test_fast_forward_is_deterministic() {
+        let rules = rules(r#"[{"compound": "*", "after_days": 2, "rate": 0.2}]"#);
+        let mut reaction_table = ReactionTable::default();
+
+        let mut fermenter = Fermenter::new(3);
+        let mut day_by_day = compounds();
+        for _ in 0..20 {
+            rules.ferment_day(
+                &mut fermenter,
+                &mut day_by_day,
+                &mut reaction_table,
+                &UniformWeighting,
+            );
+        }
+
+        let mut fast_forwarded_fermenter = Fermenter::new(3);
+        let mut fast_forwarded = compounds();
+        rules.ferment(
+            &mut fast_forwarded_fermenter,
+            &mut fast_forwarded,
+            20,
+            &mut reaction_table,
+            &UniformWeighting,
+        );
+
+        assert_eq!(fermenter, fast_forwarded_fermenter);
+        assert_eq!(fermenter.age, 20);
+        assert_eq!(day_by_day, fast_forwarded);
+        assert_ne!(day_by_day, compounds());
+    }
+
+    #[test]
+    fn test_rules_follow_age() {
+        let rules = rules(r#"[{"compound": "7A", "after_days": 10, "until_days": 20, "rate": 1}]"#);
+        let seven_a: Compound = "7A".parse().unwrap();
+        assert_eq!(rules.rate(&seven_a, 9), 0.0);
+        assert_eq!(rules.rate(&seven_a, 10), 1.0);
+        assert_eq!(rules.rate(&seven_a, 20), 0.0);
+        assert_eq!(rules.rate(&"BE".parse().unwrap(), 15), 0.0);
+
+        let mut fermenter = Fermenter::new(0);
+        let mut young = compounds();
+        rules.ferment(
+            &mut fermenter,
+            &mut young,
+            10,
+            &mut ReactionTable::default(),
+            &UniformWeighting,
+        );
+        assert_eq!(young, compounds());
+    }
+
+    #[test]
+    fn test_fermenting_ignores_query_order() {
+        use bevy::ecs::schedule::{Stage, SystemStage};
+
+        let mut world = World::new();
+        world.insert_resource(rules(r#"[{"compound": "*", "rate": 0.5}]"#));
+        world.insert_resource(ReactionTable::<COMPOUND_WEIGHT>::default());
+        world.insert_resource(BrewingWeighting::default());
+
+        let forwards = world.spawn().insert(Fermenter::new(5)).id();
+        let backwards = world.spawn().insert(Fermenter::new(5)).id();
+        for compound in compounds() {
+            world
+                .spawn()
+                .insert_bundle((compound, ContainedIn(forwards)));
+        }
+        for compound in compounds().into_iter().rev() {
+            world
+                .spawn()
+                .insert_bundle((compound, ContainedIn(backwards)));
+        }
+
+        let mut stage = SystemStage::single(fermenting.system());
+        for _ in 0..10 {
+            stage.run(&mut world);
+        }
+
+        let mut contents = |fermenter: Entity| {
+            let mut compounds = world
+                .query::<(&Compound, &ContainedIn)>()
+                .iter(&world)
+                .filter(|(_, contained_in)| contained_in.0 == fermenter)
+                .map(|(compound, _)| compound.clone())
+                .collect::<Vec<Compound>>();
+            compounds.sort();
+            compounds
+        };
+        let fermented = contents(forwards);
+        assert_eq!(fermented, contents(backwards));
+
+        let mut original = compounds();
+        original.sort();
+        assert_ne!(fermented, original);
+    }
+
+    #[test]
+    fn test_day_seeds_differ_across_fermenters() {
+        let fermenter = |seed, age| Fermenter { seed, age };
+        assert_ne!(day_seed(&fermenter(1, 2)), day_seed(&fermenter(2, 1)));
+        assert_ne!(day_seed(&fermenter(1, 2)), day_seed(&fermenter(1, 3)));
+        assert_eq!(day_seed(&fermenter(0, 0)), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(day_seed(&fermenter(1, 2)), 0xc485_8308_e594_9c49);
+        assert_eq!(day_seed(&fermenter(2, 1)), 0xb370_3ad8_9450_7022);
+    }
+
+    #[test]
+    fn test_rate_out_of_range() {
+        match parse_design::<FermentationRules>(r#"[{"compound": "7A", "rate": 1.5}]"#) {
+            Err(DesignError::InvalidError(diagnostics)) => {
+                assert_eq!(diagnostics[0].path, "$[0].rate");
+                assert_eq!(
+                    diagnostics[0].error,
+                    FieldError::RangeError {
+                        value: 1.5,
+                        min: 0.0,
+                        max: 1.0,
+                    }
+                );
+            }
+            _ => panic!("Fermentation rules should be invalid"),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::alchemy::{components::*, compound::Compound, pattern::CompoundPattern};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+/// A filter that holds back every compound matching its pattern, like "contains E".
+#[serde_as]
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Filter(#[serde_as(as = "DisplayFromStr")] pub CompoundPattern);
+
+/// Send this to pour a cauldron through the `Filter` on the `filter` entity.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct FilterCauldron {
+    pub cauldron: Entity,
+    pub filter: Entity,
+}
+
+/// Sent once a cauldron has been filtered, with the `Residue` that was held back.
+/// When the filter doesn't hold anything back there's no residue, and this isn't sent.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Filtered {
+    pub cauldron: Entity,
+    pub residue: Entity,
+}
+
+pub fn filtering(
+    mut commands: Commands,
+    mut filter_events: EventReader<FilterCauldron>,
+    mut filtered_events: EventWriter<Filtered>,
+    mut compound_query: Query<(&Compound, &mut ContainedIn)>,
+    filter_query: Query<&Filter>,
+) {
+    for FilterCauldron { cauldron, filter } in filter_events.iter() {
+        let filter = match filter_query.get(*filter) {
+            Ok(filter) => filter,
+            Err(_) => {
+                warn!("Tried to filter with {:?}, which isn't a filter", filter);
+                continue;
+            }
+        };
+
+        let mut held_back = compound_query
+            .iter_mut()
+            .filter(|(compound, contained_in)| {
+                contained_in.0 == *cauldron && filter.0.matches(*compound)
+            })
+            .map(|(_, contained_in)| contained_in)
+            .peekable();
+        if held_back.peek().is_none() {
+            continue;
+        }
+
+        let residue = commands.spawn().insert(Residue).id();
+        for mut contained_in in held_back {
+            contained_in.0 = residue;
+        }
+
+        filtered_events.send(Filtered {
+            cauldron: *cauldron,
+            residue,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::compound::CompoundError;
+    use bevy::{
+        app::Events,
+        ecs::schedule::{Stage, SystemStage},
+    };
+
+    #[test]
+    fn test_filtering() -> Result<(), CompoundError> {
+        let mut world = World::new();
+        world.insert_resource(Events::<FilterCauldron>::default());
+        world.insert_resource(Events::<Filtered>::default());
+
+        let filter = world
+            .spawn()
+            .insert(serde_json::from_str::<Filter>(r#""contains E""#).unwrap())
+            .id();
+        let cauldron = world.spawn().insert(Cauldron).id();
+        let empty = world.spawn().insert(Cauldron).id();
+        for compound in ["BE", "7A", "2AE"] {
+            world
+                .spawn()
+                .insert_bundle((compound.parse::<Compound>()?, ContainedIn(cauldron)));
+        }
+        world
+            .spawn()
+            .insert_bundle(("7A".parse::<Compound>()?, ContainedIn(empty)));
+
+        let mut filter_events = world.get_resource_mut::<Events<FilterCauldron>>().unwrap();
+        filter_events.send(FilterCauldron { cauldron, filter });
+        // Nothing in this one is held back.
+        filter_events.send(FilterCauldron {
+            cauldron: empty,
+            filter,
+        });
+        SystemStage::single(filtering.system()).run(&mut world);
+
+        let events = world.get_resource::<Events<Filtered>>().unwrap();
+        let filtered = events
+            .get_reader()
+            .iter(events)
+            .copied()
+            .collect::<Vec<Filtered>>();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].cauldron, cauldron);
+        assert_eq!(world.query::<&Residue>().iter(&world).count(), 1);
+
+        let mut contents = |container: Entity| {
+            let mut compounds = world
+                .query::<(&Compound, &ContainedIn)>()
+                .iter(&world)
+                .filter(|(_, contained_in)| contained_in.0 == container)
+                .map(|(compound, _)| compound.to_string())
+                .collect::<Vec<String>>();
+            compounds.sort();
+            compounds
+        };
+        assert_eq!(contents(cauldron), vec!["7A"]);
+        assert_eq!(contents(filtered[0].residue), vec!["2AE", "BE"]);
+        assert_eq!(contents(empty), vec!["7A"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+use crate::alchemy::{
+    components::*,
+    compound::Compound,
+    design::{DesignData, FieldError},
+    mixture::Mixture,
+    resources::BrewingRng,
+};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+/// Some amount of a compound in an ingredient.
+#[serde_as]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct IngredientCompound {
+    #[serde_as(as = "DisplayFromStr")]
+    pub compound: Compound,
+    pub count: u32,
+    /// Each addition of the ingredient has up to this many more or fewer of the compound.
+    #[serde(default)]
+    pub variance: u32,
+    /// How well a `Mortar` has to be worked for this compound to be pressed out, from 0 to 1.
+    #[serde(default)]
+    pub min_quality: f32,
+}
+
+impl IngredientCompound {
+    /// How many of the compound are in one addition of the ingredient.
+    pub fn roll_count<R: Rng + ?Sized>(&self, rng: &mut R) -> u32 {
+        if self.variance == 0 {
+            self.count
+        } else {
+            rng.gen_range(self.count.saturating_sub(self.variance)..=self.count + self.variance)
+        }
+    }
+}
+
+/// Something that can be put into a cauldron, like "Moonleaf".
+/// Like everything else in the world, ingredients are made up of compounds.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Ingredient {
+    pub name: String,
+    /// Either a list of `IngredientCompound`s, or a mixture like "6×A3B + 4×BE" when
+    /// none of the compounds vary.
+    #[serde(deserialize_with = "deserialize_ingredient_compounds")]
+    pub compounds: Vec<IngredientCompound>,
+}
+
+fn deserialize_ingredient_compounds<'de, D>(
+    deserializer: D,
+) -> Result<Vec<IngredientCompound>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum IngredientCompounds {
+        Mixture(String),
+        List(Vec<IngredientCompound>),
+    }
+
+    match IngredientCompounds::deserialize(deserializer)? {
+        IngredientCompounds::Mixture(mixture) => Ok(mixture
+            .parse::<Mixture>()
+            .map_err(serde::de::Error::custom)?
+            .0
+            .into_iter()
+            .map(|(compound, count)| IngredientCompound {
+                compound,
+                count,
+                variance: 0,
+                min_quality: 0.0,
+            })
+            .collect()),
+        IngredientCompounds::List(compounds) => Ok(compounds),
+    }
+}
+
+impl Ingredient {
+    /// The compounds in one addition of this ingredient.
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<Compound> {
+        let mut compounds = Vec::new();
+        for ingredient_compound in &self.compounds {
+            let count = ingredient_compound.roll_count(rng);
+            compounds.extend((0..count).map(|_| ingredient_compound.compound.clone()));
+        }
+        compounds
+    }
+}
+
+/// Design data defining every ingredient, as authored in `assets/design/ingredients.json`.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Ingredients(pub Vec<Ingredient>);
+
+impl DesignData for Ingredients {
+    const NAME: &'static str = "ingredients";
+    const PATH: &'static str = "assets/design/ingredients.json";
+
+    fn validate(&self) -> Vec<(String, FieldError)> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(index, ingredient)| {
+                let other_index = self.0[..index]
+                    .iter()
+                    .position(|other| other.name == ingredient.name)?;
+                Some((
+                    format!("$[{}].name", index),
+                    FieldError::RepeatedError {
+                        value: format!("{:?}", ingredient.name),
+                        path: format!("$[{}]", other_index),
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+impl Ingredients {
+    pub fn get(&self, name: &str) -> Option<&Ingredient> {
+        self.0.iter().find(|ingredient| ingredient.name == name)
+    }
+}
+
+/// Send this to add an ingredient, by name, to a cauldron.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct AddIngredient {
+    pub ingredient: String,
+    pub cauldron: Entity,
+}
+
+pub fn adding_ingredients(
+    mut commands: Commands,
+    mut add_events: EventReader<AddIngredient>,
+    ingredients: Res<Ingredients>,
+    mut brewing_rng: ResMut<BrewingRng>,
+) {
+    for AddIngredient {
+        ingredient,
+        cauldron,
+    } in add_events.iter()
+    {
+        match ingredients.get(ingredient) {
+            Some(ingredient) => {
+                for compound in ingredient.roll(&mut brewing_rng.0) {
+                    commands
+                        .spawn()
+                        .insert(compound)
+                        .insert(ContainedIn(*cauldron));
+                }
+            }
+            None => warn!("Tried to add unknown ingredient {:?}", ingredient),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::design::{parse_design, DesignError};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_roll_within_variance() {
+        let ingredients = parse_design::<Ingredients>(
+            r#"[{
+                "name": "Moonleaf",
+                "compounds": [
+                    {"compound": "A3B", "count": 12, "variance": 2},
+                    {"compound": "7A", "count": 3}
+                ]
+            }]"#,
+        )
+        .unwrap();
+        let moonleaf = ingredients.get("Moonleaf").unwrap();
+        let (a3b, seven_a): (Compound, Compound) = ("A3B".parse().unwrap(), "7A".parse().unwrap());
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..20 {
+            let compounds = moonleaf.roll(&mut rng);
+            let a3b_count = compounds.iter().filter(|c| **c == a3b).count();
+            assert!((10..=14).contains(&a3b_count));
+            assert_eq!(compounds.iter().filter(|c| **c == seven_a).count(), 3);
+        }
+    }
+
+    #[test]
+    fn test_mixture_ingredient() {
+        let ingredients =
+            parse_design::<Ingredients>(r#"[{"name": "Duskcap", "compounds": "6×A3B + 4x7a"}]"#)
+                .unwrap();
+        let duskcap = ingredients.get("Duskcap").unwrap();
+        let compounds: Mixture = duskcap.roll(&mut StdRng::seed_from_u64(0)).iter().collect();
+        assert_eq!(compounds, "6×A3B + 4×7A".parse().unwrap());
+
+        assert!(
+            parse_design::<Ingredients>(r#"[{"name": "Duskcap", "compounds": "6×A3"}]"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_duplicate_ingredients() {
+        let ingredients = parse_design::<Ingredients>(
+            r#"[
+                {"name": "Moonleaf", "compounds": []},
+                {"name": "Moonleaf", "compounds": []}
+            ]"#,
+        );
+        match ingredients {
+            Err(DesignError::InvalidError(diagnostics)) => {
+                assert_eq!(diagnostics[0].path, "$[1].name");
+                assert_eq!(diagnostics[0].line, Some(3));
+                assert_eq!(
+                    diagnostics[0].error,
+                    FieldError::RepeatedError {
+                        value: "\"Moonleaf\"".to_string(),
+                        path: "$[0]".to_string()
+                    }
+                );
+            }
+            _ => panic!("Ingredients should be invalid"),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::alchemy::{design::DesignData, effect::Effects};
+use serde::{Deserialize, Serialize};
+
+/// How effects in a mixture act on one another.
+/// An effect is only considered present while its magnitude is positive.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "interaction")]
+pub enum EffectInteraction {
+    /// Two opposing effects weaken each other, until the weaker of them is gone.
+    Cancel { kind: String, against: String },
+    /// An effect is multiplied by `factor` whenever `with` is present.
+    Amplify {
+        kind: String,
+        with: String,
+        factor: f32,
+    },
+    /// Where every effect in `from` is present, they combine into `into`.
+    /// As much of each is used up as the weakest of them has.
+    Transform { from: Vec<String>, into: String },
+    /// An effect is lost entirely unless its magnitude reaches `minimum`.
+    Threshold { kind: String, minimum: f32 },
+}
+
+impl EffectInteraction {
+    pub fn apply(&self, effects: &mut Effects) {
+        match self {
+            EffectInteraction::Cancel { kind, against } => {
+                let cancelled = effects.magnitude(kind).min(effects.magnitude(against));
+                if cancelled > 0.0 {
+                    *effects.0.entry(kind.clone()).or_default() -= cancelled;
+                    *effects.0.entry(against.clone()).or_default() -= cancelled;
+                }
+            }
+            EffectInteraction::Amplify { kind, with, factor } => {
+                if effects.magnitude(with) > 0.0 {
+                    if let Some(magnitude) = effects.0.get_mut(kind) {
+                        *magnitude *= factor;
+                    }
+                }
+            }
+            EffectInteraction::Transform { from, into } => {
+                let transformed = from
+                    .iter()
+                    .map(|kind| effects.magnitude(kind))
+                    .fold(f32::INFINITY, f32::min);
+                if transformed > 0.0 && transformed.is_finite() {
+                    for kind in from {
+                        *effects.0.entry(kind.clone()).or_default() -= transformed;
+                    }
+                    *effects.0.entry(into.clone()).or_default() += transformed;
+                }
+            }
+            EffectInteraction::Threshold { kind, minimum } => {
+                if effects.magnitude(kind) < *minimum {
+                    effects.0.remove(kind);
+                }
+            }
+        }
+        effects.0.retain(|_, magnitude| *magnitude != 0.0);
+    }
+}
+
+/// Design data deciding how effects interact, as authored in
+/// `assets/design/effect_interactions.json`.
+/// Interactions are applied in the order they're listed.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct EffectInteractions(pub Vec<EffectInteraction>);
+
+impl DesignData for EffectInteractions {
+    const NAME: &'static str = "effect interactions";
+    const PATH: &'static str = "assets/design/effect_interactions.json";
+}
+
+impl EffectInteractions {
+    /// Resolve the effects of a mixture into its net effects.
+    pub fn resolve(&self, effects: &Effects) -> Effects {
+        let mut effects = effects.clone();
+        for interaction in &self.0 {
+            interaction.apply(&mut effects);
+        }
+        effects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::design::parse_design;
+
+    fn effects(magnitudes: &[(&str, f32)]) -> Effects {
+        Effects(
+            magnitudes
+                .iter()
+                .map(|(kind, magnitude)| (kind.to_string(), *magnitude))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_cancel() {
+        let interactions = parse_design::<EffectInteractions>(
+            r#"[{"interaction": "Cancel", "kind": "Warmth", "against": "Chill"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            interactions.resolve(&effects(&[("Warmth", 3.0), ("Chill", 1.0)])),
+            effects(&[("Warmth", 2.0)])
+        );
+    }
+
+    #[test]
+    fn test_amplify() {
+        let interactions = parse_design::<EffectInteractions>(
+            r#"[{"interaction": "Amplify", "kind": "Vigor", "with": "Warmth", "factor": 2.0}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            interactions.resolve(&effects(&[("Vigor", 1.5), ("Warmth", 1.0)])),
+            effects(&[("Vigor", 3.0), ("Warmth", 1.0)])
+        );
+        assert_eq!(
+            interactions.resolve(&effects(&[("Vigor", 1.5)])),
+            effects(&[("Vigor", 1.5)])
+        );
+    }
+
+    #[test]
+    fn test_transform() {
+        let interactions = parse_design::<EffectInteractions>(
+            r#"[{"interaction": "Transform", "from": ["Levity", "Clarity"], "into": "Flight"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            interactions.resolve(&effects(&[("Levity", 2.0), ("Clarity", 0.5)])),
+            effects(&[("Levity", 1.5), ("Flight", 0.5)])
+        );
+    }
+
+    #[test]
+    fn test_threshold() {
+        let interactions = parse_design::<EffectInteractions>(
+            r#"[{"interaction": "Threshold", "kind": "Sleep", "minimum": 2.0}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            interactions.resolve(&effects(&[("Sleep", 1.0), ("Vigor", 1.0)])),
+            effects(&[("Vigor", 1.0)])
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use crate::alchemy::{
+    compound::{Compound, CompoundError},
+    element_counts::{lenient_element_counts_parser, ElementCounts},
+};
+use nom::{
+    branch,
+    bytes::complete::tag,
+    character::complete::{self, multispace0},
+    combinator, multi, sequence, IResult,
+};
+use std::{collections::BTreeMap, convert::TryFrom, fmt, iter::FromIterator, str::FromStr};
+
+/// Some number of each of a few compounds, like the contents of a cauldron.
+///
+/// Written as compounds with optional counts joined by "+", like "12×A3B + 3×7A".
+/// "x" works in place of "×", and compounds are parsed leniently, so "12x3ba + 3x7a" is the same.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Mixture(pub BTreeMap<Compound, u32>);
+
+impl Mixture {
+    pub fn add(&mut self, compound: Compound, count: u32) {
+        *self.0.entry(compound).or_insert(0) += count;
+    }
+
+    /// Add every compound of `other` to this mixture.
+    pub fn extend(&mut self, other: Mixture) {
+        for (compound, count) in other.0 {
+            self.add(compound, count);
+        }
+    }
+
+    /// The number of compounds in the mixture, counting repeats.
+    pub fn len(&self) -> u32 {
+        self.0.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every compound in the mixture, repeated as many times as there are of it.
+    pub fn compounds(&self) -> impl Iterator<Item = &Compound> {
+        self.0
+            .iter()
+            .flat_map(|(compound, count)| (0..*count).map(move |_| compound))
+    }
+}
+
+impl<'a> FromIterator<&'a Compound> for Mixture {
+    fn from_iter<I: IntoIterator<Item = &'a Compound>>(compounds: I) -> Mixture {
+        let mut mixture = Mixture::default();
+        for compound in compounds {
+            mixture.add(compound.clone(), 1);
+        }
+        mixture
+    }
+}
+
+fn mixture_entry_parser(input: &str) -> IResult<&str, (u32, ElementCounts)> {
+    let (input, count) = combinator::opt(sequence::terminated(
+        complete::u32,
+        sequence::tuple((multispace0, branch::alt((tag("×"), tag("x"))), multispace0)),
+    ))(input)?;
+    let (input, element_counts) = lenient_element_counts_parser(input)?;
+    Ok((input, (count.unwrap_or(1), element_counts)))
+}
+
+/// Parses a mixture of element counts, see `Mixture` for the notation.
+pub fn mixture_parser(input: &str) -> IResult<&str, Vec<(u32, ElementCounts)>> {
+    multi::separated_list1(
+        sequence::tuple((multispace0, tag("+"), multispace0)),
+        mixture_entry_parser,
+    )(input)
+}
+
+impl FromStr for Mixture {
+    type Err = CompoundError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let entries = match combinator::all_consuming(mixture_parser)(value) {
+            Ok((_, entries)) => entries,
+            Err(e) => {
+                let rest = match e {
+                    nom::Err::Error(e) | nom::Err::Failure(e) => e.input,
+                    nom::Err::Incomplete(_) => "",
+                };
+                return Err(CompoundError::ParseError {
+                    input: value.to_string(),
+                    offset: value.len() - rest.len(),
+                    found: rest.chars().next(),
+                    expected: if rest.starts_with('+') {
+                        "a compound after the `+`".to_string()
+                    } else {
+                        "a compound, or `+` between compounds".to_string()
+                    },
+                });
+            }
+        };
+
+        let mut mixture = Mixture::default();
+        for (count, element_counts) in entries {
+            mixture.add(Compound::try_from(element_counts)?, count);
+        }
+        Ok(mixture)
+    }
+}
+
+impl fmt::Display for Mixture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.0
+                .iter()
+                .map(|(compound, count)| match count {
+                    1 => compound.to_string(),
+                    count => format!("{}×{}", count, compound),
+                })
+                .collect::<Vec<String>>()
+                .join(" + ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixture_parsing() -> Result<(), CompoundError> {
+        let mixture: Mixture = "12×A3B + 3×7A".parse()?;
+        assert_eq!(mixture.0[&"A3B".parse()?], 12);
+        assert_eq!(mixture.0[&"7A".parse()?], 3);
+        assert_eq!(mixture.len(), 15);
+        assert_eq!(mixture.compounds().count(), 15);
+
+        assert_eq!("12x3ba+2×7A + 7a".parse::<Mixture>()?, mixture);
+        assert_eq!("2×2(AB)A".parse::<Mixture>()?.to_string(), "2×3A2B");
+        Ok(())
+    }
+
+    #[test]
+    fn test_mixture_display_round_trip() -> Result<(), CompoundError> {
+        let mixture: Mixture = "BE + 20×A3B + 3×7A".parse()?;
+        assert_eq!(mixture.to_string(), "BE + 20×A3B + 3×7A");
+        assert_eq!(mixture.to_string().parse::<Mixture>()?, mixture);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mixture_errors() {
+        assert!(matches!(
+            "12×A3B + ".parse::<Mixture>(),
+            Err(CompoundError::ParseError {
+                offset: 8,
+                found: Some('+'),
+                ..
+            })
+        ));
+        assert_eq!(
+            "12×A3B + 2×AB".parse::<Mixture>(),
+            Err(CompoundError::SizeError { size: 3 })
+        );
+    }
+}
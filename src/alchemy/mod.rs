@@ -1,24 +1,133 @@
 use crate::AppState;
 use bevy::{core::FixedTimestep, prelude::*};
 
+pub mod bands;
 pub mod components;
 pub mod compound;
 #[cfg(feature = "dev")]
 pub mod debug;
-mod element;
+pub mod design;
+pub mod distillation;
+pub mod effect;
+pub mod element;
 mod element_counts;
+pub mod extraction;
+pub mod fermentation;
+pub mod filter;
+pub mod ingredient;
+pub mod interaction;
+pub mod mixture;
+pub mod mortar;
+pub mod pattern;
+pub mod potion;
+pub mod reaction_table;
 pub mod resources;
+pub mod stirring;
 pub mod systems;
+pub mod temperature;
+pub mod validation;
+pub mod weighting;
+
+/// How many seconds pass between each brewing tick.
+pub const BREWING_TICK: f64 = 0.1;
+
+/// Labels for the systems run on the brewing tick, which have to run in a set order.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BrewingSystem {
+    /// Sets the `Heat` that extracting and brewing read.
+    Heating,
+    /// Shares the `BrewingRng` with brewing, so has to keep to the same place in the order.
+    Extracting,
+    Brewing,
+}
+
+/// Labels for the systems that move compounds out of containers,
+/// which are ordered so that a compound can't leave a container twice in a frame.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ContainerSystem {
+    Filtering,
+    Distilling,
+    /// Despawns whatever's left, which is deferred to the end of the stage, so has to come last.
+    Bottling,
+}
 
 pub struct BrewingPlugin;
 
 impl Plugin for BrewingPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_startup_system(resources::insert_reaction_rules.system())
+        app.init_resource::<resources::ReactionRulePaths>()
+            .init_resource::<resources::BrewingRng>()
+            .init_resource::<resources::BrewingWeighting>()
+            .init_resource::<reaction_table::ReactionTable<{ compound::COMPOUND_WEIGHT }>>()
+            .add_event::<distillation::Distill>()
+            .add_event::<distillation::Distilled>()
+            .add_event::<extraction::SteepIngredient>()
+            .add_event::<fermentation::FastForwardFermenter>()
+            .add_event::<filter::FilterCauldron>()
+            .add_event::<filter::Filtered>()
+            .add_event::<ingredient::AddIngredient>()
+            .add_event::<mortar::ExpressIngredient>()
+            .add_event::<mortar::Expressed>()
+            .add_event::<mortar::StrikePestle>()
+            .add_event::<potion::BottleCauldron>()
+            .add_event::<potion::PotionBottled>()
+            .add_event::<stirring::StirInput>()
+            .add_event::<temperature::AddFuel>()
+            .add_event::<temperature::PumpBellows>()
+            .add_asset::<resources::ReactionRuleSet>()
+            .add_startup_system(resources::insert_reaction_rules.system())
+            .add_startup_system(resources::load_reaction_rule_layers.system())
+            .add_startup_system(design::insert_design::<effect::EffectTable>.system())
+            .add_startup_system(design::insert_design::<interaction::EffectInteractions>.system())
+            .add_startup_system(design::insert_design::<ingredient::Ingredients>.system())
+            .add_startup_system(design::insert_design::<distillation::DistillationRules>.system())
+            .add_startup_system(design::insert_design::<fermentation::FermentationRules>.system())
+            .add_startup_system(design::insert_design::<extraction::ExtractionTechniques>.system())
+            .add_startup_system(design::insert_design::<temperature::TemperatureSettings>.system())
+            .add_startup_system(design::insert_design::<stirring::StirSettings>.system())
+            .add_system(systems::update_reaction_rules.system())
+            .add_system(ingredient::adding_ingredients.system())
+            .add_system(
+                filter::filtering
+                    .system()
+                    .label(ContainerSystem::Filtering)
+                    .before(ContainerSystem::Distilling),
+            )
+            .add_system(
+                distillation::distilling
+                    .system()
+                    .label(ContainerSystem::Distilling)
+                    .before(ContainerSystem::Bottling),
+            )
+            .add_system(potion::bottling.system().label(ContainerSystem::Bottling))
+            .add_system(fermentation::fast_forwarding_fermenters.system())
+            .add_system(extraction::steeping_ingredients.system())
+            .add_system(mortar::striking_pestles.system())
+            .add_system(mortar::expressing.system())
+            .add_system(temperature::stoking.system())
+            .add_system(stirring::stirring.system())
+            .add_system_set(
+                SystemSet::on_update(AppState::Brewing)
+                    .with_run_criteria(FixedTimestep::step(BREWING_TICK))
+                    .with_system(
+                        temperature::heating
+                            .system()
+                            .label(BrewingSystem::Heating)
+                            .before(BrewingSystem::Brewing),
+                    )
+                    .with_system(
+                        extraction::extracting
+                            .system()
+                            .label(BrewingSystem::Extracting)
+                            .after(BrewingSystem::Heating)
+                            .before(BrewingSystem::Brewing),
+                    )
+                    .with_system(systems::brewing.system().label(BrewingSystem::Brewing)),
+            )
             .add_system_set(
                 SystemSet::on_update(AppState::Brewing)
-                    .with_run_criteria(FixedTimestep::step(0.1))
-                    .with_system(systems::brewing.system()),
+                    .with_run_criteria(FixedTimestep::step(fermentation::FERMENTATION_DAY))
+                    .with_system(fermentation::fermenting.system()),
             );
     }
 }
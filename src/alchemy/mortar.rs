@@ -0,0 +1,210 @@
+use crate::alchemy::{
+    components::*,
+    compound::Compound,
+    ingredient::{Ingredient, Ingredients},
+    resources::BrewingRng,
+};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// The share of the compounds pressed out of an ingredient even with the worst technique.
+pub const MIN_EXPRESSION_YIELD: f32 = 0.25;
+
+/// The rhythm a pestle should be worked at, striking once every `beat` seconds for `strikes` strikes.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PestleRhythm {
+    pub beat: f64,
+    pub strikes: usize,
+}
+
+impl Default for PestleRhythm {
+    fn default() -> PestleRhythm {
+        PestleRhythm {
+            beat: 0.5,
+            strikes: 8,
+        }
+    }
+}
+
+impl PestleRhythm {
+    /// How well strikes at `strike_times`, in seconds, keep to this rhythm, from 0 to 1.
+    /// Each interval between strikes counts against the quality by how far it is off the beat,
+    /// and stopping short of `strikes` strikes scales it down.
+    pub fn quality(&self, strike_times: &[f64]) -> f32 {
+        if strike_times.len() < 2 {
+            return 0.0;
+        }
+
+        let timing = 1.0
+            - strike_times
+                .windows(2)
+                .map(|pair| ((pair[1] - pair[0] - self.beat).abs() / self.beat).min(1.0))
+                .sum::<f64>()
+                / (strike_times.len() - 1) as f64;
+        let completeness = (strike_times.len() as f64 / self.strikes as f64).min(1.0);
+        (timing * completeness) as f32
+    }
+}
+
+/// The compounds pressed out of one of `ingredient` in a mortar worked with `quality`, from 0 to 1.
+/// Compounds needing a better quality than that stay in the pulp,
+/// and fewer of the rest come out the worse the quality is.
+pub fn express<R: Rng + ?Sized>(
+    ingredient: &Ingredient,
+    quality: f32,
+    rng: &mut R,
+) -> Vec<Compound> {
+    let expression_yield = MIN_EXPRESSION_YIELD + (1.0 - MIN_EXPRESSION_YIELD) * quality;
+    let mut compounds = Vec::new();
+    for ingredient_compound in &ingredient.compounds {
+        if quality < ingredient_compound.min_quality {
+            continue;
+        }
+        for _ in 0..ingredient_compound.roll_count(rng) {
+            if rng.gen::<f32>() < expression_yield {
+                compounds.push(ingredient_compound.compound.clone());
+            }
+        }
+    }
+    compounds
+}
+
+/// A mortar and pestle, keeping the times it's been struck at since it was last emptied.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Mortar {
+    pub rhythm: PestleRhythm,
+    pub strike_times: Vec<f64>,
+}
+
+/// Send this whenever the player strikes the pestle in a `Mortar`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct StrikePestle(pub Entity);
+
+/// Send this to press an ingredient, by name, in a mortar, pouring its juice into `container`.
+/// The quality of the pressing comes from the strikes since the mortar was last emptied.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct ExpressIngredient {
+    pub ingredient: String,
+    pub mortar: Entity,
+    pub container: Entity,
+}
+
+/// Sent once an ingredient has been pressed, with how well the mortar was worked.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Expressed {
+    pub mortar: Entity,
+    pub container: Entity,
+    pub quality: f32,
+}
+
+pub fn striking_pestles(
+    mut strike_events: EventReader<StrikePestle>,
+    mut mortar_query: Query<&mut Mortar>,
+    time: Res<Time>,
+) {
+    for StrikePestle(mortar) in strike_events.iter() {
+        match mortar_query.get_mut(*mortar) {
+            Ok(mut mortar) => mortar.strike_times.push(time.seconds_since_startup()),
+            Err(_) => warn!("Tried to strike {:?}, which isn't a mortar", mortar),
+        }
+    }
+}
+
+pub fn expressing(
+    mut commands: Commands,
+    mut express_events: EventReader<ExpressIngredient>,
+    mut expressed_events: EventWriter<Expressed>,
+    mut mortar_query: Query<&mut Mortar>,
+    ingredients: Res<Ingredients>,
+    mut brewing_rng: ResMut<BrewingRng>,
+) {
+    for ExpressIngredient {
+        ingredient,
+        mortar,
+        container,
+    } in express_events.iter()
+    {
+        let ingredient = match ingredients.get(ingredient) {
+            Some(ingredient) => ingredient,
+            None => {
+                warn!("Tried to express unknown ingredient {:?}", ingredient);
+                continue;
+            }
+        };
+        let mut mortar_component = match mortar_query.get_mut(*mortar) {
+            Ok(mortar) => mortar,
+            Err(_) => {
+                warn!("Tried to express in {:?}, which isn't a mortar", mortar);
+                continue;
+            }
+        };
+
+        let quality = mortar_component
+            .rhythm
+            .quality(&mortar_component.strike_times);
+        mortar_component.strike_times.clear();
+        for compound in express(ingredient, quality, &mut brewing_rng.0) {
+            commands
+                .spawn()
+                .insert(compound)
+                .insert(ContainedIn(*container));
+        }
+
+        expressed_events.send(Expressed {
+            mortar: *mortar,
+            container: *container,
+            quality,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::design::parse_design;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_rhythm_quality() {
+        let rhythm = PestleRhythm {
+            beat: 0.5,
+            strikes: 5,
+        };
+        assert_eq!(rhythm.quality(&[0.0, 0.5, 1.0, 1.5, 2.0]), 1.0);
+        assert_eq!(rhythm.quality(&[0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0]), 1.0);
+        assert_eq!(rhythm.quality(&[0.0, 0.5, 1.0]), 0.6);
+        assert!((rhythm.quality(&[0.0, 0.75, 1.25, 1.75, 2.25]) - 0.875).abs() < 1e-6);
+        assert_eq!(rhythm.quality(&[0.0, 2.0, 4.0, 6.0, 8.0]), 0.0);
+        assert_eq!(rhythm.quality(&[1.0]), 0.0);
+        assert_eq!(rhythm.quality(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_expression_depends_on_quality() {
+        let ingredients = parse_design::<Ingredients>(
+            r#"[{
+                "name": "Emberroot",
+                "compounds": [
+                    {"compound": "BE", "count": 100, "min_quality": 0.5},
+                    {"compound": "A3B", "count": 100}
+                ]
+            }]"#,
+        )
+        .unwrap();
+        let emberroot = ingredients.get("Emberroot").unwrap();
+        let be: Compound = "BE".parse().unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let poor = express(emberroot, 0.0, &mut rng);
+        assert!(poor.iter().all(|compound| *compound != be));
+        assert!((10..=40).contains(&poor.len()));
+
+        let perfect = express(emberroot, 1.0, &mut rng);
+        assert_eq!(perfect.len(), 200);
+        assert_eq!(
+            perfect.iter().filter(|compound| **compound == be).count(),
+            100
+        );
+    }
+}
@@ -0,0 +1,553 @@
+use crate::alchemy::{
+    compound::{display_parse_error, Alchemical},
+    element::{element_parser_maker, Element},
+    element_counts::element_count_parser,
+};
+use nom::{
+    branch,
+    bytes::complete::tag,
+    character::complete::{self, multispace0, multispace1},
+    combinator, error, multi, sequence, IResult,
+};
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PatternError {
+    #[error("{}", display_parse_error("compound pattern", .pattern, *.offset, .found, .expected))]
+    ParseError {
+        pattern: String,
+        /// Byte offset in `pattern` of where parsing stopped.
+        offset: usize,
+        /// The character at `offset`, or None at the end of `pattern`.
+        found: Option<char>,
+        expected: String,
+    },
+}
+
+/// How many of an element a compound may have, from `min` to `max` inclusive.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct ElementConstraint {
+    pub element: Element,
+    pub min: u32,
+    /// None means there's no upper limit.
+    pub max: Option<u32>,
+}
+
+impl ElementConstraint {
+    fn holds(&self, count: u32) -> bool {
+        count >= self.min && self.max.map_or(true, |max| count <= max)
+    }
+
+    fn is_exact(&self) -> bool {
+        self.max == Some(self.min)
+    }
+}
+
+/// Describes a group of compounds, in the same notation as compounds themselves.
+///
+/// - "A3B" only matches A3B itself.
+/// - "≥3A" (or ">=3A") matches compounds with at least 3 A, "≤B" (or "<=B") at most 1 B,
+///   "1-3A" from 1 to 3 A, and "any D" any amount of D.
+///   As soon as there's one of these, elements that aren't mentioned can be anything.
+///   "A≥2B" matches compounds with exactly 1 A and at least 2 B.
+/// - "contains E" matches compounds with at least 1 E, "contains 2AB" at least 2 A and 1 B.
+/// - "*" matches every compound.
+/// - "!BE" (or "not BE") matches every compound but BE.
+/// - "7A | contains E" matches compounds matching either pattern.
+///   Parentheses group patterns, like "!(7A | BE)".
+///
+/// Like compounds, elements have to be in element table order.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum CompoundPattern {
+    Any,
+    Elements {
+        constraints: Vec<ElementConstraint>,
+        /// Whether elements without a constraint may be present.
+        others: bool,
+    },
+    Not(Box<CompoundPattern>),
+    Or(Vec<CompoundPattern>),
+}
+
+impl Default for CompoundPattern {
+    fn default() -> CompoundPattern {
+        CompoundPattern::Any
+    }
+}
+
+impl<const W: u32> From<&Alchemical<W>> for CompoundPattern {
+    /// A pattern only matching `alchemical`.
+    fn from(alchemical: &Alchemical<W>) -> CompoundPattern {
+        CompoundPattern::Elements {
+            constraints: Element::iter()
+                .map(|element| (element, alchemical.element_count(element)))
+                .filter(|(_, count)| *count > 0)
+                .map(|(element, count)| ElementConstraint {
+                    element,
+                    min: count,
+                    max: Some(count),
+                })
+                .collect(),
+            others: false,
+        }
+    }
+}
+
+impl CompoundPattern {
+    pub fn matches<const W: u32>(&self, alchemical: &Alchemical<W>) -> bool {
+        match self {
+            CompoundPattern::Any => true,
+            CompoundPattern::Elements {
+                constraints,
+                others,
+            } => Element::iter().all(|element| {
+                let count = alchemical.element_count(element);
+                match constraints.iter().find(|c| c.element == element) {
+                    Some(constraint) => constraint.holds(count),
+                    None => *others || count == 0,
+                }
+            }),
+            CompoundPattern::Not(pattern) => !pattern.matches(alchemical),
+            CompoundPattern::Or(patterns) => patterns.iter().any(|p| p.matches(alchemical)),
+        }
+    }
+
+    /// Every alchemical of weight `W` matching this pattern, in ascending order.
+    pub fn all<const W: u32>(&self) -> impl Iterator<Item = Alchemical<W>> + '_ {
+        Alchemical::all().filter(move |alchemical| self.matches(alchemical))
+    }
+}
+
+fn comparison_parser(element: Element) -> impl Fn(&str) -> IResult<&str, ElementConstraint> {
+    move |input: &str| {
+        let at_least = branch::alt((tag("≥"), tag(">=")));
+        let at_most = branch::alt((tag("≤"), tag("<=")));
+        branch::alt((
+            combinator::map(
+                sequence::preceded(at_least, element_count_parser(element)),
+                |(element, count)| ElementConstraint {
+                    element,
+                    min: count,
+                    max: None,
+                },
+            ),
+            combinator::map(
+                sequence::preceded(at_most, element_count_parser(element)),
+                |(element, count)| ElementConstraint {
+                    element,
+                    min: 0,
+                    max: Some(count),
+                },
+            ),
+        ))(input)
+    }
+}
+
+fn range_parser(element: Element) -> impl Fn(&str) -> IResult<&str, ElementConstraint> {
+    move |input: &str| {
+        let (input, (min, _, max, element)) = sequence::tuple((
+            complete::u32,
+            tag("-"),
+            complete::u32,
+            element_parser_maker(element),
+        ))(input)?;
+        Ok((
+            input,
+            ElementConstraint {
+                element,
+                min,
+                max: Some(max),
+            },
+        ))
+    }
+}
+
+fn any_parser(element: Element) -> impl Fn(&str) -> IResult<&str, ElementConstraint> {
+    move |input: &str| {
+        let (input, element) = sequence::preceded(
+            sequence::pair(tag("any"), multispace1),
+            element_parser_maker(element),
+        )(input)?;
+        Ok((
+            input,
+            ElementConstraint {
+                element,
+                min: 0,
+                max: None,
+            },
+        ))
+    }
+}
+
+fn exact_parser(element: Element) -> impl Fn(&str) -> IResult<&str, ElementConstraint> {
+    move |input: &str| {
+        let (input, (element, count)) = element_count_parser(element)(input)?;
+        Ok((
+            input,
+            ElementConstraint {
+                element,
+                min: count,
+                max: Some(count),
+            },
+        ))
+    }
+}
+
+fn constraint_parser(element: Element) -> impl Fn(&str) -> IResult<&str, ElementConstraint> {
+    move |input: &str| {
+        branch::alt((
+            any_parser(element),
+            comparison_parser(element),
+            range_parser(element),
+            exact_parser(element),
+        ))(input)
+    }
+}
+
+/// Element constraints in element table order, like "A≥2B any D".
+/// With `at_least`, every count is a minimum, like in "contains 2AB".
+fn constraints_parser(at_least: bool) -> impl Fn(&str) -> IResult<&str, Vec<ElementConstraint>> {
+    move |input: &str| {
+        let mut constraints = Vec::new();
+        let mut input = input;
+        for element in Element::iter() {
+            // Constraints may be separated by whitespace, like in "A3B any D".
+            let (after_space, _) = multispace0(input)?;
+            let start = if constraints.is_empty() {
+                input
+            } else {
+                after_space
+            };
+            let parsed = if at_least {
+                combinator::opt(combinator::map(
+                    element_count_parser(element),
+                    |(element, count)| ElementConstraint {
+                        element,
+                        min: count,
+                        max: None,
+                    },
+                ))(start)?
+            } else {
+                combinator::opt(constraint_parser(element))(start)?
+            };
+            if let (remaining, Some(constraint)) = parsed {
+                constraints.push(constraint);
+                input = remaining;
+            }
+        }
+        if constraints.is_empty() {
+            return Err(nom::Err::Error(error::Error::new(
+                input,
+                error::ErrorKind::Many1,
+            )));
+        }
+        Ok((input, constraints))
+    }
+}
+
+fn elements_parser(input: &str) -> IResult<&str, CompoundPattern> {
+    branch::alt((
+        combinator::map(
+            sequence::preceded(
+                sequence::pair(tag("contains"), multispace1),
+                constraints_parser(true),
+            ),
+            |constraints| CompoundPattern::Elements {
+                constraints,
+                others: true,
+            },
+        ),
+        combinator::map(constraints_parser(false), |constraints| {
+            CompoundPattern::Elements {
+                others: !constraints.iter().all(ElementConstraint::is_exact),
+                constraints,
+            }
+        }),
+    ))(input)
+}
+
+fn primary_parser(input: &str) -> IResult<&str, CompoundPattern> {
+    branch::alt((
+        sequence::delimited(
+            sequence::pair(tag("("), multispace0),
+            compound_pattern_parser,
+            sequence::pair(multispace0, tag(")")),
+        ),
+        combinator::value(CompoundPattern::Any, tag("*")),
+        elements_parser,
+    ))(input)
+}
+
+fn negation_parser(input: &str) -> IResult<&str, CompoundPattern> {
+    branch::alt((
+        combinator::map(
+            sequence::preceded(
+                branch::alt((
+                    sequence::pair(tag("!"), multispace0),
+                    sequence::pair(tag("not"), multispace1),
+                )),
+                negation_parser,
+            ),
+            |pattern| CompoundPattern::Not(Box::new(pattern)),
+        ),
+        primary_parser,
+    ))(input)
+}
+
+/// Parses a compound pattern, see `CompoundPattern` for the notation.
+pub fn compound_pattern_parser(input: &str) -> IResult<&str, CompoundPattern> {
+    let (input, mut patterns) = multi::separated_list1(
+        sequence::tuple((multispace0, tag("|"), multispace0)),
+        negation_parser,
+    )(input)?;
+    if patterns.len() == 1 {
+        Ok((input, patterns.remove(0)))
+    } else {
+        Ok((input, CompoundPattern::Or(patterns)))
+    }
+}
+
+impl FromStr for CompoundPattern {
+    type Err = PatternError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let trimmed = value.trim_start();
+        combinator::all_consuming(compound_pattern_parser)(trimmed.trim_end())
+            .map(|(_, pattern)| pattern)
+            .map_err(|e| {
+                let rest = match e {
+                    nom::Err::Error(e) | nom::Err::Failure(e) => e.input,
+                    nom::Err::Incomplete(_) => "",
+                };
+                let offset = value.len() - trimmed.len() + trimmed.trim_end().len() - rest.len();
+                PatternError::ParseError {
+                    pattern: value.to_string(),
+                    offset,
+                    found: value[offset..].chars().next(),
+                    expected: diagnose_parse_error(&value[..offset], &value[offset..]),
+                }
+            })
+    }
+}
+
+/// What should have come next in a compound pattern that failed to parse,
+/// going by what's `before` where parsing stopped and the `rest` from there on.
+/// Parsing backs off to the start of whatever it failed to finish, like a "contains" missing its counts,
+/// so that's usually what `rest` starts with.
+fn diagnose_parse_error(before: &str, rest: &str) -> String {
+    let rest = rest.trim_start();
+    let last_element = Element::iter().find(|e| before.ends_with(e.symbol()));
+    let next_element = Element::iter().find_map(|e| element_parser_maker(e)(rest).ok());
+    let starts = [
+        ("|", "a compound pattern after the `|`"),
+        ("(", "a compound pattern and a `)` after the `(`"),
+        ("!", "a compound pattern after the `!`"),
+        ("not", "a compound pattern after the `not`"),
+        ("contains", "element counts after the `contains`"),
+        ("any", "an element after the `any`"),
+        ("≥", "an element count after the `≥`"),
+        (">=", "an element count after the `>=`"),
+        ("≤", "an element count after the `≤`"),
+        ("<=", "an element count after the `<=`"),
+    ];
+
+    if let Some((start, expected)) = starts.iter().find(|(start, _)| rest.starts_with(start)) {
+        // Patterns after a `|` or negation are parsed on their own, so diagnose them on their own.
+        let after = &rest[start.len()..];
+        if ["|", "!", "not"].contains(start) && !after.trim().is_empty() {
+            diagnose_parse_error("", after)
+        } else {
+            expected.to_string()
+        }
+    } else if rest.starts_with(|c: char| c.is_ascii_digit()) && next_element.is_none() {
+        "an element after the count".to_string()
+    } else if let (Some(last), Some((_, next))) = (last_element, next_element) {
+        if next <= last {
+            format!("an element that can follow {}", last)
+        } else {
+            "`|` between compound patterns".to_string()
+        }
+    } else if before.trim().is_empty() {
+        "a compound pattern".to_string()
+    } else {
+        "`|` between compound patterns".to_string()
+    }
+}
+
+impl fmt::Display for ElementConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = |count: u32| {
+            if count == 1 {
+                String::new()
+            } else {
+                count.to_string()
+            }
+        };
+        match (self.min, self.max) {
+            (0, None) => write!(f, "any {}", self.element),
+            (min, None) => write!(f, "≥{}{}", count(min), self.element),
+            (0, Some(max)) => write!(f, "≤{}{}", count(max), self.element),
+            (min, Some(max)) if min == max => write!(f, "{}{}", count(min), self.element),
+            (min, Some(max)) => write!(f, "{}-{}{}", min, max, self.element),
+        }
+    }
+}
+
+impl fmt::Display for CompoundPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompoundPattern::Any => write!(f, "*"),
+            CompoundPattern::Elements { constraints, .. } => {
+                for (i, constraint) in constraints.iter().enumerate() {
+                    // "any" needs to be kept apart from the element before it.
+                    if i > 0 && constraint.min == 0 && constraint.max.is_none() {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", constraint)?;
+                }
+                Ok(())
+            }
+            CompoundPattern::Not(pattern) => match pattern.as_ref() {
+                CompoundPattern::Or(_) => write!(f, "!({})", pattern),
+                _ => write!(f, "!{}", pattern),
+            },
+            CompoundPattern::Or(patterns) => write!(
+                f,
+                "{}",
+                patterns
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" | ")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::compound::{Compound, COMPOUND_WEIGHT};
+
+    fn matching(pattern: &str) -> Vec<String> {
+        let pattern: CompoundPattern = pattern.parse().unwrap();
+        pattern
+            .all::<COMPOUND_WEIGHT>()
+            .map(|compound| compound.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_exact_pattern() {
+        assert_eq!(matching("A3B"), vec!["A3B"]);
+    }
+
+    #[test]
+    fn test_comparison_patterns() {
+        assert!(matching("≥3A")
+            .iter()
+            .all(|c| c.starts_with(char::is_numeric)));
+        assert!(matching(">=3A").contains(&"3A2B".to_string()));
+        assert!(!matching(">=3A").contains(&"A3B".to_string()));
+        assert_eq!(matching("≥5A≤B"), matching(">=5A<=B"));
+        assert_eq!(matching("≥6A"), vec!["7A"]);
+    }
+
+    #[test]
+    fn test_range_patterns() {
+        let one_to_three_a = matching("1-3A any B");
+        assert!(one_to_three_a.contains(&"3A2B".to_string()));
+        assert!(one_to_three_a.contains(&"A3B".to_string()));
+        assert!(one_to_three_a.contains(&"2AE".to_string()));
+        assert!(!one_to_three_a.contains(&"7A".to_string()));
+        assert!(!one_to_three_a.contains(&"BE".to_string()));
+        assert_eq!(matching("1-3A"), matching("1-3A any B any C any D any E"));
+    }
+
+    #[test]
+    fn test_contains_pattern() {
+        let containing_e = matching("contains E");
+        assert!(containing_e.contains(&"BE".to_string()));
+        assert!(containing_e.contains(&"2AE".to_string()));
+        assert!(!containing_e.contains(&"7A".to_string()));
+    }
+
+    #[test]
+    fn test_any_pattern() {
+        assert_eq!(matching("*").len(), Compound::all().count());
+    }
+
+    #[test]
+    fn test_negation_and_alternation() {
+        assert_eq!(matching("7A | BE"), vec!["BE", "7A"]);
+        assert_eq!(matching("7A|BE"), matching("(BE) | 7A"));
+        assert_eq!(matching("!(7A | BE)").len(), Compound::all().count() - 2);
+        assert_eq!(matching("not 7A"), matching("!7A"));
+        assert_eq!(matching("contains E | !contains E"), matching("*"));
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        for pattern in ["", "BA", "≥", "contains", "A3B*", "7A |", "(7A", "!", "any"] {
+            assert!(pattern.parse::<CompoundPattern>().is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        assert_eq!(
+            " 7A | BA".parse::<CompoundPattern>(),
+            Err(PatternError::ParseError {
+                pattern: " 7A | BA".to_string(),
+                offset: 7,
+                found: Some('A'),
+                expected: "an element that can follow B".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_error_expected() {
+        let expected = |pattern: &str| match pattern.parse::<CompoundPattern>() {
+            Err(PatternError::ParseError { expected, .. }) => expected,
+            result => panic!("{} should fail to parse, got {:?}", pattern, result),
+        };
+        assert_eq!(expected(""), "a compound pattern");
+        assert_eq!(expected("A3B*"), "`|` between compound patterns");
+        assert_eq!(expected("7A |"), "a compound pattern after the `|`");
+        assert_eq!(expected("7A | !any"), "an element after the `any`");
+        assert_eq!(
+            expected("(7A"),
+            "a compound pattern and a `)` after the `(`"
+        );
+        assert_eq!(expected("contains"), "element counts after the `contains`");
+        assert_eq!(expected("≥3A≤"), "an element count after the `≤`");
+        assert_eq!(expected("1-3 A"), "an element after the count");
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for pattern in [
+            "*",
+            "A3B",
+            "≥3A≤B",
+            "contains 2AE",
+            "1-3A any B",
+            "!(7A | BE) | !contains E",
+        ] {
+            let parsed: CompoundPattern = pattern.parse().unwrap();
+            assert_eq!(parsed.to_string().parse::<CompoundPattern>(), Ok(parsed));
+        }
+    }
+
+    #[test]
+    fn test_pattern_from_compound() -> Result<(), crate::alchemy::compound::CompoundError> {
+        let compound: Compound = "2AE".parse()?;
+        assert_eq!(
+            CompoundPattern::from(&compound),
+            "2AE".parse::<CompoundPattern>().unwrap()
+        );
+        Ok(())
+    }
+}
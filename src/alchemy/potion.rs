@@ -0,0 +1,264 @@
+use crate::alchemy::{
+    components::*,
+    compound::Compound,
+    effect::{EffectTable, Effects},
+    interaction::EffectInteractions,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::collections::{BTreeMap, HashSet};
+
+/// The result of brewing, bottled from a cauldron.
+#[serde_as]
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Potion {
+    /// How many of each compound went into the bottle.
+    #[serde_as(as = "BTreeMap<DisplayFromStr, _>")]
+    pub composition: BTreeMap<Compound, u32>,
+    /// The total number of compounds in the bottle.
+    pub volume: u32,
+    /// Net effects of the potion, after the effects of its compounds have interacted.
+    pub effects: Effects,
+    /// How much of the potion's effects are its strongest effect, from 0 to 1.
+    /// A potion without effects has no purity to speak of.
+    pub purity: f32,
+}
+
+impl Potion {
+    pub fn new<'a, I>(
+        compounds: I,
+        effect_table: &EffectTable,
+        effect_interactions: &EffectInteractions,
+    ) -> Potion
+    where
+        I: IntoIterator<Item = &'a Compound>,
+    {
+        let compounds = compounds.into_iter().collect::<Vec<&Compound>>();
+        let mut composition: BTreeMap<Compound, u32> = BTreeMap::new();
+        for compound in &compounds {
+            *composition.entry((*compound).clone()).or_insert(0) += 1;
+        }
+
+        let effects = effect_interactions.resolve(&effect_table.population_effects(compounds));
+
+        let total = effects.0.values().map(|m| m.abs()).sum::<f32>();
+        let strongest = effects.0.values().map(|m| m.abs()).fold(0.0, f32::max);
+        let purity = if total > 0.0 { strongest / total } else { 0.0 };
+
+        Potion {
+            volume: composition.values().sum(),
+            composition,
+            effects,
+            purity,
+        }
+    }
+}
+
+/// Send this to bottle everything in a cauldron.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct BottleCauldron(pub Entity);
+
+/// Sent once a cauldron has been bottled, and its compounds have been despawned.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PotionBottled {
+    pub cauldron: Entity,
+    pub potion: Potion,
+}
+
+/// Has to run after anything else that moves compounds out of a cauldron this frame,
+/// since the despawning is deferred and they'd otherwise leave the cauldron twice.
+pub fn bottling(
+    mut commands: Commands,
+    mut bottle_events: EventReader<BottleCauldron>,
+    mut bottled_events: EventWriter<PotionBottled>,
+    compound_query: Query<(Entity, &Compound, &ContainedIn)>,
+    effect_table: Res<EffectTable>,
+    effect_interactions: Res<EffectInteractions>,
+) {
+    // Despawning is deferred to the end of the stage,
+    // so bottling a cauldron again in the same frame would bottle the same compounds twice.
+    let mut bottled = HashSet::new();
+    for BottleCauldron(cauldron) in bottle_events.iter() {
+        if !bottled.insert(*cauldron) {
+            continue;
+        }
+
+        let mut compounds = Vec::new();
+        for (entity, compound, ContainedIn(container)) in compound_query.iter() {
+            if container == cauldron {
+                compounds.push(compound);
+                commands.entity(entity).despawn();
+            }
+        }
+
+        bottled_events.send(PotionBottled {
+            cauldron: *cauldron,
+            potion: Potion::new(compounds, &effect_table, &effect_interactions),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::{
+        compound::CompoundError,
+        design::parse_design,
+        distillation::{distilling, Distill, DistillationRules, Distilled},
+        resources::ReactionRule,
+        ContainerSystem,
+    };
+    use bevy::{
+        app::Events,
+        ecs::schedule::{Stage, SystemStage},
+    };
+
+    fn effect_table() -> EffectTable {
+        parse_design(
+            r#"{
+                "elements": {
+                    "A": [{"kind": "Levity", "magnitude": 1.0}],
+                    "B": [{"kind": "Warmth", "magnitude": 1.0}],
+                    "E": [{"kind": "Clarity", "magnitude": 2.0}]
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_potion_from_compounds() -> Result<(), CompoundError> {
+        let compounds: Vec<Compound> = vec!["7A".parse()?, "7A".parse()?, "BE".parse()?];
+        let potion = Potion::new(&compounds, &effect_table(), &EffectInteractions::default());
+
+        assert_eq!(potion.volume, 3);
+        assert_eq!(potion.composition[&"7A".parse()?], 2);
+        assert_eq!(potion.effects.magnitude("Levity"), 14.0);
+        assert_eq!(potion.effects.magnitude("Clarity"), 2.0);
+        assert_eq!(potion.effects.magnitude("Warmth"), 1.0);
+        assert_eq!(potion.purity, 14.0 / 17.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_potion_serde_round_trip() -> Result<(), CompoundError> {
+        let compounds: Vec<Compound> = vec!["A3B".parse()?, "7A".parse()?];
+        let potion = Potion::new(&compounds, &effect_table(), &EffectInteractions::default());
+        let json = serde_json::to_string(&potion).unwrap();
+        assert_eq!(serde_json::from_str::<Potion>(&json).unwrap(), potion);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bottling_despawns_only_that_cauldron() -> Result<(), CompoundError> {
+        let seven_a: Compound = "7A".parse()?;
+
+        let mut world = World::new();
+        world.insert_resource(effect_table());
+        world.insert_resource(EffectInteractions::default());
+        world.insert_resource(Events::<BottleCauldron>::default());
+        world.insert_resource(Events::<PotionBottled>::default());
+
+        let bottled = world.spawn().insert(Cauldron).id();
+        let untouched = world.spawn().insert(Cauldron).id();
+        for cauldron in [bottled, untouched] {
+            for _ in 0..5 {
+                world
+                    .spawn()
+                    .insert_bundle((seven_a.clone(), ContainedIn(cauldron)));
+            }
+        }
+
+        // Bottling twice in one frame only bottles the compounds once.
+        for _ in 0..2 {
+            world
+                .get_resource_mut::<Events<BottleCauldron>>()
+                .unwrap()
+                .send(BottleCauldron(bottled));
+        }
+        SystemStage::single(bottling.system()).run(&mut world);
+
+        let events = world.get_resource::<Events<PotionBottled>>().unwrap();
+        let potions = events
+            .get_reader()
+            .iter(events)
+            .cloned()
+            .collect::<Vec<PotionBottled>>();
+        assert_eq!(potions.len(), 1);
+        assert_eq!(potions[0].cauldron, bottled);
+        assert_eq!(potions[0].potion.volume, 5);
+
+        let remaining = world
+            .query::<&ContainedIn>()
+            .iter(&world)
+            .map(|ContainedIn(container)| *container)
+            .collect::<Vec<Entity>>();
+        assert_eq!(remaining, vec![untouched; 5]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_distilled_compounds_are_not_bottled() -> Result<(), CompoundError> {
+        let (seven_a, be): (Compound, Compound) = ("7A".parse()?, "BE".parse()?);
+
+        let mut world = World::new();
+        world.insert_resource(effect_table());
+        world.insert_resource(EffectInteractions::default());
+        world.insert_resource(
+            parse_design::<DistillationRules>(
+                r#"[{"name": "Clarify", "criterion": {"property": "ContainsElement", "element": "E", "min_count": 1}}]"#,
+            )
+            .unwrap(),
+        );
+        world.insert_resource(Vec::<ReactionRule>::new());
+        world.insert_resource(Events::<BottleCauldron>::default());
+        world.insert_resource(Events::<PotionBottled>::default());
+        world.insert_resource(Events::<Distill>::default());
+        world.insert_resource(Events::<Distilled>::default());
+
+        let cauldron = world.spawn().insert(Cauldron).id();
+        for compound in [&seven_a, &be, &be] {
+            world
+                .spawn()
+                .insert_bundle((compound.clone(), ContainedIn(cauldron)));
+        }
+
+        // Bottled before being distilled in the same frame.
+        world
+            .get_resource_mut::<Events<BottleCauldron>>()
+            .unwrap()
+            .send(BottleCauldron(cauldron));
+        world
+            .get_resource_mut::<Events<Distill>>()
+            .unwrap()
+            .send(Distill {
+                cauldron,
+                rule: "Clarify".to_string(),
+            });
+        SystemStage::parallel()
+            .with_system(
+                distilling
+                    .system()
+                    .label(ContainerSystem::Distilling)
+                    .before(ContainerSystem::Bottling),
+            )
+            .with_system(bottling.system().label(ContainerSystem::Bottling))
+            .run(&mut world);
+
+        let events = world.get_resource::<Events<PotionBottled>>().unwrap();
+        let potion = &events.get_reader().iter(events).next().unwrap().potion;
+        assert_eq!(
+            potion.composition.keys().collect::<Vec<_>>(),
+            vec![&seven_a]
+        );
+        assert_eq!(
+            world
+                .query::<&Compound>()
+                .iter(&world)
+                .collect::<Vec<&Compound>>(),
+            vec![&be, &be]
+        );
+        Ok(())
+    }
+}
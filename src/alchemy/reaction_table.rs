@@ -0,0 +1,132 @@
+use crate::alchemy::{compound::Alchemical, weighting::OutcomeWeighting};
+use rand::Rng;
+use std::collections::HashMap;
+
+/// Cache of the possible reactions between pairs of alchemicals.
+/// Computing these is expensive, but there are only so many alchemicals of a given weight,
+/// so brewing looks them up here instead of computing them for every collision.
+///
+/// The possible reactions of a pair only depend on the elements they contain between them,
+/// so they're the same regardless of which alchemical is on the left.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ReactionTable<const W: u32> {
+    reactions: HashMap<(Alchemical<W>, Alchemical<W>), Vec<(Alchemical<W>, Alchemical<W>)>>,
+}
+
+impl<const W: u32> ReactionTable<W> {
+    fn key(left: &Alchemical<W>, right: &Alchemical<W>) -> (Alchemical<W>, Alchemical<W>) {
+        if left <= right {
+            (left.clone(), right.clone())
+        } else {
+            (right.clone(), left.clone())
+        }
+    }
+
+    /// All possible reactions between `left` and `right`, sorted.
+    /// Computed on the first lookup of the pair, and cached for later lookups.
+    pub fn possible_reactions(
+        &mut self,
+        left: &Alchemical<W>,
+        right: &Alchemical<W>,
+    ) -> &[(Alchemical<W>, Alchemical<W>)] {
+        self.reactions
+            .entry(Self::key(left, right))
+            .or_insert_with(|| {
+                let mut possible_reactions = left
+                    .set_of_possible_reactions(right)
+                    .into_iter()
+                    .collect::<Vec<(Alchemical<W>, Alchemical<W>)>>();
+                possible_reactions.sort();
+                possible_reactions
+            })
+    }
+
+    /// Compute the reactions between every pair of the given alchemicals ahead of time.
+    pub fn precompute<'a, I>(&mut self, alchemicals: I)
+    where
+        I: IntoIterator<Item = &'a Alchemical<W>>,
+    {
+        let alchemicals = alchemicals.into_iter().collect::<Vec<&Alchemical<W>>>();
+        for (i, left) in alchemicals.iter().enumerate() {
+            for right in &alchemicals[i..] {
+                self.possible_reactions(left, right);
+            }
+        }
+    }
+
+    /// Same as `Alchemical::react_weighted`, but using the cached reactions.
+    pub fn react<O, R>(
+        &mut self,
+        left: &mut Alchemical<W>,
+        right: &mut Alchemical<W>,
+        weighting: &O,
+        rng: &mut R,
+    ) where
+        O: OutcomeWeighting<W> + ?Sized,
+        R: Rng + ?Sized,
+    {
+        let possible_reactions = self.possible_reactions(left, right);
+        left.react_among(right, possible_reactions, weighting, rng);
+    }
+
+    pub fn len(&self) -> usize {
+        self.reactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reactions.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::{
+        compound::{Compound, CompoundError},
+        weighting::UniformWeighting,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_cached_reactions_match_computed() -> Result<(), CompoundError> {
+        let mut reaction_table = ReactionTable::default();
+        let left: Compound = "2AE".parse()?;
+        let right: Compound = "A3B".parse()?;
+
+        let expected = left.set_of_possible_reactions(&right);
+        for (l, r) in [(&left, &right), (&right, &left)] {
+            assert_eq!(
+                reaction_table
+                    .possible_reactions(l, r)
+                    .iter()
+                    .cloned()
+                    .collect::<HashSet<(Compound, Compound)>>(),
+                expected
+            );
+        }
+        assert_eq!(reaction_table.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_react_matches_react_weighted() -> Result<(), CompoundError> {
+        let mut reaction_table = ReactionTable::default();
+        let (mut left, mut right): (Compound, Compound) = ("2ABC".parse()?, "BE".parse()?);
+        let (mut cached_left, mut cached_right) = (left.clone(), right.clone());
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut cached_rng = StdRng::seed_from_u64(1);
+
+        for _ in 0..10 {
+            left.react_weighted(&mut right, &UniformWeighting, &mut rng);
+            reaction_table.react(
+                &mut cached_left,
+                &mut cached_right,
+                &UniformWeighting,
+                &mut cached_rng,
+            );
+            assert_eq!((&left, &right), (&cached_left, &cached_right));
+        }
+        Ok(())
+    }
+}
@@ -1,25 +1,348 @@
-use crate::alchemy::{components::*, compound::Compound};
-use bevy::prelude::*;
+use crate::alchemy::{
+    components::*,
+    compound::{Compound, COMPOUND_WEIGHT},
+    pattern::CompoundPattern,
+    validation::{validate_layered_rules, validate_reaction_rules, ReactionRulesError},
+    weighting::{OutcomeWeighting, UniformWeighting},
+};
+use bevy::{prelude::*, reflect::TypeUuid};
+use rand::{rngs::StdRng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
-use std::{fs, io};
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
 #[serde_as]
 #[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct ReactionRule {
+    /// The compounds this rule covers, like "7A" or "contains E"
     #[serde_as(as = "DisplayFromStr")]
-    pub compound: Compound,
+    pub compound: CompoundPattern,
     /// Setting to None means this compound reacts under any heat
     pub heat: Option<Heat>,
     /// Setting to None means this compound reacts under any stir method
     pub stir_method: Option<StirMethod>,
+    /// How favored this compound is as a reaction product when using a `RuleWeighting`.
+    /// Setting to None is the same as a weight of 1
+    #[serde(default)]
+    pub weight: Option<u32>,
 }
 
-pub fn load_reaction_rules() -> io::Result<Vec<ReactionRule>> {
-    let data = fs::read_to_string("assets/design/reaction_rules.json")?;
-    Ok(serde_json::from_str(&data)?)
+impl ReactionRule {
+    pub fn covers(&self, compound: &Compound) -> bool {
+        self.compound.matches(compound)
+    }
+
+    /// Every compound this rule covers, in ascending order.
+    pub fn compounds(&self) -> impl Iterator<Item = Compound> + '_ {
+        self.compound.all()
+    }
+
+    /// Whether this rule applies under the given criteria,
+    /// where None means there is no requirement for that criteria.
+    pub fn applies(&self, stir_method: Option<StirMethod>, heat: Option<Heat>) -> bool {
+        let stir_match = match (stir_method, self.stir_method) {
+            (Some(sm), Some(rule_sm)) => sm == rule_sm,
+            _ => true,
+        };
+        let heat_match = match (heat, self.heat) {
+            (Some(h), Some(rule_h)) => h == rule_h,
+            _ => true,
+        };
+        stir_match && heat_match
+    }
+}
+
+/// The compounds covered by each of the `Vec<ReactionRule>`, in the same order,
+/// so that patterns are only expanded when the rules are inserted or replaced.
+/// Has to be kept in sync whenever the rules are.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ReactionRuleCompounds(pub Vec<Vec<Compound>>);
+
+impl ReactionRuleCompounds {
+    pub fn new(reaction_rules: &[ReactionRule]) -> ReactionRuleCompounds {
+        ReactionRuleCompounds(
+            reaction_rules
+                .iter()
+                .map(|rule| rule.compounds().collect())
+                .collect(),
+        )
+    }
+
+    /// Get all compounds that react under the given criteria according to `reaction_rules`,
+    /// the rules these compounds were expanded from.
+    pub fn reactive_compounds<'a>(
+        &'a self,
+        reaction_rules: &[ReactionRule],
+        stir_method: Option<StirMethod>,
+        heat: Option<Heat>,
+    ) -> BTreeSet<&'a Compound> {
+        reaction_rules
+            .iter()
+            .zip(&self.0)
+            .filter(|(rule, _)| rule.applies(stir_method, heat))
+            .flat_map(|(_, compounds)| compounds)
+            .collect()
+    }
 }
 
-pub fn insert_reaction_rules(mut commands: Commands) {
-    commands.insert_resource(load_reaction_rules().expect("Failed to load reaction rules"))
+pub const REACTION_RULES_PATH: &str = "assets/design/reaction_rules.json";
+
+/// Reaction rules as an asset, so that changes to the rules files can be swapped in while running.
+#[derive(Clone, Eq, PartialEq, Debug, Default, TypeUuid)]
+#[uuid = "6f3c1a2e-8d4b-4f7e-9a51-2c0e7b3d9f14"]
+pub struct ReactionRuleSet(pub Vec<ReactionRule>);
+
+impl ReactionRuleSet {
+    pub fn from_reader<R: io::Read>(mut reader: R) -> Result<ReactionRuleSet, ReactionRulesError> {
+        let mut data = String::new();
+        reader.read_to_string(&mut data)?;
+        validate_reaction_rules(&data)
+            .map(ReactionRuleSet)
+            .map_err(ReactionRulesError::InvalidError)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ReactionRuleSet, ReactionRulesError> {
+        fs::File::open(&path)
+            .map_err(ReactionRulesError::from)
+            .and_then(ReactionRuleSet::from_reader)
+            .map_err(|e| ReactionRulesError::FileError {
+                path: path.as_ref().display().to_string(),
+                source: Box::new(e),
+            })
+    }
+
+    /// Load each of the rule sets at `paths`, layering each one on top of the last.
+    pub fn load_layered<P: AsRef<Path>>(
+        paths: &[P],
+    ) -> Result<ReactionRuleSet, ReactionRulesError> {
+        ReactionRuleSet::from_layers(
+            paths
+                .iter()
+                .map(ReactionRuleSet::load)
+                .collect::<Result<Vec<ReactionRuleSet>, ReactionRulesError>>()?,
+        )
+    }
+
+    /// Layer each of `layers` on top of the last,
+    /// checking that the rules of different layers don't clash like the rules of a single file can't.
+    pub fn from_layers<I: IntoIterator<Item = ReactionRuleSet>>(
+        layers: I,
+    ) -> Result<ReactionRuleSet, ReactionRulesError> {
+        let mut reaction_rule_set = ReactionRuleSet::default();
+        for layer in layers {
+            reaction_rule_set.layer(layer);
+        }
+        let diagnostics = validate_layered_rules(&reaction_rule_set.0);
+        if diagnostics.is_empty() {
+            Ok(reaction_rule_set)
+        } else {
+            Err(ReactionRulesError::LayeredError(diagnostics))
+        }
+    }
+
+    /// Put the rules of `other` on top of these, like a mod overriding the base game.
+    /// Rules in `other` replace the rules here for the same compound pattern, the rest are added.
+    pub fn layer(&mut self, other: ReactionRuleSet) {
+        for rule in other.0 {
+            match self.0.iter_mut().find(|r| r.compound == rule.compound) {
+                Some(existing_rule) => *existing_rule = rule,
+                None => self.0.push(rule),
+            }
+        }
+    }
+}
+
+/// Files that the reaction rules are loaded from, each layered on top of the last.
+/// Insert this before adding the `BrewingPlugin` to brew with other rules.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ReactionRulePaths(pub Vec<PathBuf>);
+
+impl ReactionRulePaths {
+    /// Paths given with `--rules` or `-r`, or the default rules if there aren't any.
+    pub fn from_args(args: &[String]) -> ReactionRulePaths {
+        let paths = args
+            .windows(2)
+            .filter(|pair| pair[0] == "--rules" || pair[0] == "-r")
+            .map(|pair| PathBuf::from(&pair[1]))
+            .collect::<Vec<PathBuf>>();
+
+        if paths.is_empty() {
+            ReactionRulePaths::default()
+        } else {
+            ReactionRulePaths(paths)
+        }
+    }
+}
+
+impl Default for ReactionRulePaths {
+    fn default() -> ReactionRulePaths {
+        ReactionRulePaths(vec![PathBuf::from(REACTION_RULES_PATH)])
+    }
+}
+
+pub fn load_reaction_rules() -> Result<Vec<ReactionRule>, ReactionRulesError> {
+    Ok(ReactionRuleSet::load(REACTION_RULES_PATH)?.0)
+}
+
+pub fn insert_reaction_rules(mut commands: Commands, reaction_rule_paths: Res<ReactionRulePaths>) {
+    match ReactionRuleSet::load_layered(&reaction_rule_paths.0) {
+        Ok(ReactionRuleSet(reaction_rules)) => {
+            commands.insert_resource(ReactionRuleCompounds::new(&reaction_rules));
+            commands.insert_resource(reaction_rules);
+        }
+        Err(e) => panic!("Failed to load reaction rules: {}", e),
+    }
+}
+
+/// A layer of the reaction rules, loaded from `path` into the `Assets<ReactionRuleSet>`.
+pub struct ReactionRuleLayer {
+    pub path: PathBuf,
+    pub handle: Handle<ReactionRuleSet>,
+    /// When the file was last modified as of loading it, if the filesystem can tell.
+    pub modified: Option<SystemTime>,
+}
+
+impl ReactionRuleLayer {
+    /// Load the rules at `path` again if the file has been modified since they were last loaded.
+    pub fn reload_if_modified(&mut self) -> Option<Result<ReactionRuleSet, ReactionRulesError>> {
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(ReactionRuleSet::load(&self.path))
+    }
+}
+
+fn modified_time<P: AsRef<Path>>(path: P) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The layers that the `Vec<ReactionRule>` resource is kept in sync with,
+/// in the same order as the `ReactionRulePaths`.
+pub struct ReactionRuleLayers(pub Vec<ReactionRuleLayer>);
+
+pub fn load_reaction_rule_layers(
+    mut commands: Commands,
+    mut reaction_rule_sets: ResMut<Assets<ReactionRuleSet>>,
+    reaction_rule_paths: Res<ReactionRulePaths>,
+) {
+    commands.insert_resource(ReactionRuleLayers(
+        reaction_rule_paths
+            .0
+            .iter()
+            .map(|path| {
+                let modified = modified_time(path);
+                let reaction_rule_set = ReactionRuleSet::load(path)
+                    .unwrap_or_else(|e| panic!("Failed to load reaction rules: {}", e));
+                ReactionRuleLayer {
+                    path: path.clone(),
+                    handle: reaction_rule_sets.add(reaction_rule_set),
+                    modified,
+                }
+            })
+            .collect(),
+    ));
+}
+
+/// The source of randomness for brewing.
+/// Insert a seeded one before adding the `BrewingPlugin` to make brewing reproducible:
+/// the same seed and the same sequence of cauldron inputs always brew the same compounds.
+pub struct BrewingRng(pub StdRng);
+
+impl BrewingRng {
+    pub fn seed_from_u64(seed: u64) -> BrewingRng {
+        BrewingRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for BrewingRng {
+    fn default() -> BrewingRng {
+        BrewingRng(StdRng::from_entropy())
+    }
+}
+
+/// How likely each outcome of a reaction in the cauldron is.
+/// Defaults to a `UniformWeighting`.
+pub struct BrewingWeighting(pub Box<dyn OutcomeWeighting<COMPOUND_WEIGHT>>);
+
+impl Default for BrewingWeighting {
+    fn default() -> BrewingWeighting {
+        BrewingWeighting(Box::new(UniformWeighting))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::validation::RuleError;
+
+    #[test]
+    fn test_layered_rules_override_by_compound() -> Result<(), ReactionRulesError> {
+        let mut reaction_rule_set = ReactionRuleSet::from_reader(
+            r#"[
+                {"compound": "7A", "heat": "Simmering", "stir_method": null},
+                {"compound": "BE", "heat": "Boiling", "stir_method": null}
+            ]"#
+            .as_bytes(),
+        )?;
+        reaction_rule_set.layer(ReactionRuleSet::from_reader(
+            r#"[
+                {"compound": "BE", "heat": null, "stir_method": "DoubleStir"},
+                {"compound": "CD", "heat": null, "stir_method": null}
+            ]"#
+            .as_bytes(),
+        )?);
+
+        assert_eq!(
+            reaction_rule_set
+                .0
+                .iter()
+                .map(|rule| (rule.compound.to_string(), rule.heat, rule.stir_method))
+                .collect::<Vec<(String, Option<Heat>, Option<StirMethod>)>>(),
+            vec![
+                ("7A".to_string(), Some(Heat::Simmering), None),
+                ("BE".to_string(), None, Some(StirMethod::DoubleStir)),
+                ("CD".to_string(), None, None),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_layers_cannot_clash() -> Result<(), ReactionRulesError> {
+        let base = ReactionRuleSet::from_reader(
+            r#"[{"compound": "BE", "heat": "Boiling", "stir_method": null}]"#.as_bytes(),
+        )?;
+        let overlapping = ReactionRuleSet::from_reader(
+            r#"[{"compound": "contains E", "heat": null, "stir_method": null}]"#.as_bytes(),
+        )?;
+        let conflicting = ReactionRuleSet::from_reader(
+            r#"[{"compound": "7A", "heat": "Boiling", "stir_method": null}]"#.as_bytes(),
+        )?;
+
+        match ReactionRuleSet::from_layers(vec![base.clone(), overlapping]) {
+            Err(ReactionRulesError::LayeredError(diagnostics)) => {
+                assert_eq!(diagnostics[0].path, "$[1].compound");
+                assert_eq!(
+                    diagnostics[0].error,
+                    RuleError::DuplicateError {
+                        compound: "BE".to_string(),
+                        index: 0
+                    }
+                );
+            }
+            result => panic!("Layers should overlap, got {:?}", result),
+        }
+        assert!(matches!(
+            ReactionRuleSet::from_layers(vec![base, conflicting]),
+            Err(ReactionRulesError::LayeredError(_))
+        ));
+        Ok(())
+    }
 }
@@ -0,0 +1,348 @@
+use crate::alchemy::{
+    bands::{Band, Bands},
+    components::*,
+    design::DesignData,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, f32::consts::TAU};
+
+/// The speed of stirring, in turns a second, that a `StirMethod` starts at.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct StirBand {
+    pub stir_method: StirMethod,
+    pub min_speed: f32,
+}
+
+impl Band for StirBand {
+    type Value = StirMethod;
+
+    fn value(&self) -> StirMethod {
+        self.stir_method
+    }
+
+    fn min(&self) -> f32 {
+        self.min_speed
+    }
+}
+
+/// How stirring gestures are recognized, as authored in `assets/design/stirring.json`.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct StirSettings {
+    /// How many seconds of samples a gesture is recognized from.
+    pub window: f64,
+    /// The stir method bands, from the slowest up. Below the first band it's a `ZeroStir`.
+    pub bands: Bands<StirBand>,
+}
+
+impl DesignData for StirSettings {
+    const NAME: &'static str = "stir settings";
+    const PATH: &'static str = "assets/design/stirring.json";
+}
+
+impl StirSettings {
+    /// The stir method for stirring at `speed` turns a second.
+    pub fn stir_method(&self, speed: f32) -> StirMethod {
+        self.bands.get(speed).unwrap_or(StirMethod::ZeroStir)
+    }
+}
+
+/// Where the spoon was at some time, in seconds, like the cursor while dragging over the cauldron.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct StirSample {
+    pub time: f64,
+    pub position: Vec2,
+}
+
+/// What a stirring gesture was recognized as.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Stirring {
+    pub stir_method: StirMethod,
+    /// How fast the spoon went around, in turns a second.
+    pub speed: f32,
+    /// How evenly the spoon went around, from 0 for sloppy stirring to 1 for perfect circles.
+    pub consistency: f32,
+}
+
+impl Default for Stirring {
+    fn default() -> Stirring {
+        Stirring {
+            stir_method: StirMethod::ZeroStir,
+            speed: 0.0,
+            consistency: 1.0,
+        }
+    }
+}
+
+/// Recognizes stirring from the last `StirSettings::window` seconds of `StirSample`s.
+/// This is kept on a cauldron, fed by input, and sets its `StirMethod` and `StirQuality`.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct StirGesture {
+    samples: VecDeque<StirSample>,
+}
+
+impl StirGesture {
+    pub fn push(&mut self, sample: StirSample) {
+        self.samples.push_back(sample);
+    }
+
+    /// Recognize the stirring in the samples up to `now`, forgetting the samples before the window.
+    /// The spoon is taken to go around the circle that best fits the samples,
+    /// so circles anywhere, of any size, and in either direction all count.
+    pub fn recognize(&mut self, now: f64, settings: &StirSettings) -> Stirring {
+        while matches!(self.samples.front(), Some(sample) if sample.time < now - settings.window) {
+            self.samples.pop_front();
+        }
+
+        let duration = match (self.samples.front(), self.samples.back()) {
+            (Some(first), Some(last)) if self.samples.len() >= 3 => (last.time - first.time) as f32,
+            _ => return Stirring::default(),
+        };
+        let center = circle_center(self.samples.iter().map(|sample| sample.position));
+        let radii = self
+            .samples
+            .iter()
+            .map(|sample| sample.position.distance(center))
+            .collect::<Vec<f32>>();
+        let (mean_radius, radius_deviation) = mean_and_deviation(&radii);
+        if duration <= 0.0 || mean_radius <= f32::EPSILON {
+            return Stirring::default();
+        }
+
+        let angles = self
+            .samples
+            .iter()
+            .map(|sample| {
+                let offset = sample.position - center;
+                offset.y.atan2(offset.x)
+            })
+            .collect::<Vec<f32>>();
+        let mut turned = 0.0;
+        let mut angular_speeds = Vec::new();
+        for (i, pair) in angles.windows(2).enumerate() {
+            // Near the middle the angle jumps about, like when the spoon is waggled across it.
+            if radii[i] < mean_radius / 2.0 || radii[i + 1] < mean_radius / 2.0 {
+                continue;
+            }
+            // Wrapped so that crossing from -π to π is a small step rather than a whole turn.
+            let step = (pair[1] - pair[0] + TAU / 2.0).rem_euclid(TAU) - TAU / 2.0;
+            turned += step;
+            let elapsed = (self.samples[i + 1].time - self.samples[i].time) as f32;
+            if elapsed > 0.0 {
+                angular_speeds.push(step / elapsed);
+            }
+        }
+
+        let speed = turned.abs() / TAU / duration;
+        let (mean_angular_speed, angular_speed_deviation) = mean_and_deviation(&angular_speeds);
+        let roundness = 1.0 - (radius_deviation / mean_radius).min(1.0);
+        let evenness = if mean_angular_speed.abs() <= f32::EPSILON {
+            0.0
+        } else {
+            1.0 - (angular_speed_deviation / mean_angular_speed.abs()).min(1.0)
+        };
+
+        Stirring {
+            stir_method: settings.stir_method(speed),
+            speed,
+            consistency: roundness * evenness,
+        }
+    }
+}
+
+/// The center of the circle that best fits `positions`, by least squares.
+/// When they don't make out a circle, like when they're all in a line, it's their mean instead.
+fn circle_center<I: ExactSizeIterator<Item = Vec2> + Clone>(positions: I) -> Vec2 {
+    let mean = positions
+        .clone()
+        .fold(Vec2::ZERO, |sum, position| sum + position)
+        / positions.len() as f32;
+    let (mut suu, mut suv, mut svv, mut suuu, mut svvv, mut suvv, mut svuu) =
+        (0.0f32, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for position in positions {
+        let offset = position - mean;
+        let (u, v) = (offset.x, offset.y);
+        suu += u * u;
+        suv += u * v;
+        svv += v * v;
+        suuu += u * u * u;
+        svvv += v * v * v;
+        suvv += u * v * v;
+        svuu += v * u * u;
+    }
+
+    let determinant = suu * svv - suv * suv;
+    if determinant <= f32::EPSILON * suu.max(svv).powi(2) {
+        return mean;
+    }
+    let (right_u, right_v) = ((suuu + suvv) / 2.0, (svvv + svuu) / 2.0);
+    mean + Vec2::new(
+        (right_u * svv - right_v * suv) / determinant,
+        (right_v * suu - right_u * suv) / determinant,
+    )
+}
+
+fn mean_and_deviation(values: &[f32]) -> (f32, f32) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f32>()
+        / values.len() as f32;
+    (mean, variance.sqrt())
+}
+
+/// How evenly a cauldron is being stirred, from 0 to 1, as the `Stirring::consistency`.
+/// Sloppy stirring splashes the contents about rather than mixing them,
+/// so fewer compounds collide in `systems::brewing`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct StirQuality(pub f32);
+
+/// Send this with where the spoon is in a cauldron with a `StirGesture`, like every frame of a drag.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct StirInput {
+    pub cauldron: Entity,
+    pub position: Vec2,
+}
+
+/// Feeds stir input into each cauldron's `StirGesture`,
+/// and keeps its `StirMethod` and `StirQuality` up to date with what's recognized.
+pub fn stirring(
+    mut commands: Commands,
+    mut stir_events: EventReader<StirInput>,
+    mut cauldron_query: Query<(
+        Entity,
+        &mut StirGesture,
+        &mut StirMethod,
+        Option<&mut StirQuality>,
+    )>,
+    stir_settings: Res<StirSettings>,
+    time: Res<Time>,
+) {
+    let now = time.seconds_since_startup();
+    for StirInput { cauldron, position } in stir_events.iter() {
+        match cauldron_query.get_mut(*cauldron) {
+            Ok((_, mut stir_gesture, _, _)) => stir_gesture.push(StirSample {
+                time: now,
+                position: *position,
+            }),
+            Err(_) => warn!("Tried to stir {:?}, which can't be stirred", cauldron),
+        }
+    }
+
+    for (cauldron, mut stir_gesture, mut stir_method, stir_quality) in cauldron_query.iter_mut() {
+        let stirring = stir_gesture.recognize(now, &stir_settings);
+        if *stir_method != stirring.stir_method {
+            *stir_method = stirring.stir_method;
+        }
+        match stir_quality {
+            Some(mut stir_quality) => {
+                if stir_quality.0 != stirring.consistency {
+                    stir_quality.0 = stirring.consistency;
+                }
+            }
+            None => {
+                commands
+                    .entity(cauldron)
+                    .insert(StirQuality(stirring.consistency));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> StirSettings {
+        StirSettings {
+            window: 1.0,
+            bands: Bands::new(vec![
+                StirBand {
+                    stir_method: StirMethod::SingleStir,
+                    min_speed: 0.5,
+                },
+                StirBand {
+                    stir_method: StirMethod::DoubleStir,
+                    min_speed: 1.0,
+                },
+                StirBand {
+                    stir_method: StirMethod::QuadrupleStir,
+                    min_speed: 2.0,
+                },
+            ])
+            .unwrap(),
+        }
+    }
+
+    /// A second of samples at 60 per second, with the spoon at `position(time)`.
+    fn trace<F: Fn(f32) -> Vec2>(position: F) -> StirGesture {
+        let mut stir_gesture = StirGesture::default();
+        for i in 0..=60 {
+            let time = i as f32 / 60.0;
+            stir_gesture.push(StirSample {
+                time: time as f64,
+                position: position(time),
+            });
+        }
+        stir_gesture
+    }
+
+    fn circle(turns_per_second: f32, radius: f32) -> impl Fn(f32) -> Vec2 {
+        move |time| {
+            let angle = time * turns_per_second * TAU;
+            Vec2::new(100.0 + radius * angle.cos(), 50.0 + radius * angle.sin())
+        }
+    }
+
+    #[test]
+    fn test_recognize_stir_methods() {
+        let settings = settings();
+        for (turns_per_second, stir_method) in [
+            (0.2, StirMethod::ZeroStir),
+            (0.75, StirMethod::SingleStir),
+            (-1.5, StirMethod::DoubleStir),
+            (3.0, StirMethod::QuadrupleStir),
+        ] {
+            let stirring = trace(circle(turns_per_second, 20.0)).recognize(1.0, &settings);
+            assert_eq!(stirring.stir_method, stir_method);
+            assert!((stirring.speed - turns_per_second.abs()).abs() < 0.05);
+            assert!(stirring.consistency > 0.95);
+        }
+    }
+
+    #[test]
+    fn test_sloppy_stirring_is_inconsistent() {
+        let settings = settings();
+        let wobbly = |time: f32| circle(1.5, 20.0 + 10.0 * (time * 37.0).sin())(time);
+        let jerky = |time: f32| circle(1.5, 20.0)(time + 0.1 * (time * 20.0).sin());
+        let even = trace(circle(1.5, 20.0)).recognize(1.0, &settings);
+        for sloppy in [wobbly, jerky] {
+            let stirring = trace(sloppy).recognize(1.0, &settings);
+            assert_eq!(stirring.stir_method, StirMethod::DoubleStir);
+            assert!(stirring.consistency < even.consistency - 0.2);
+        }
+    }
+
+    #[test]
+    fn test_no_stirring() {
+        let settings = settings();
+        let still = trace(|_| Vec2::new(10.0, 10.0)).recognize(1.0, &settings);
+        assert_eq!(still, Stirring::default());
+
+        // Waggling the spoon back and forth doesn't get anything going around.
+        let waggle =
+            trace(|time| Vec2::new(10.0 * (time * 30.0).sin(), 0.0)).recognize(1.0, &settings);
+        assert_eq!(waggle.stir_method, StirMethod::ZeroStir);
+
+        // Once the spoon stops, the stirring is forgotten after the window.
+        let mut stopped = trace(circle(3.0, 20.0));
+        assert_eq!(
+            stopped.recognize(2.5, &settings).stir_method,
+            StirMethod::ZeroStir
+        );
+    }
+}
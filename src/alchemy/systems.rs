@@ -1,66 +1,316 @@
-use crate::alchemy::{components::*, compound::Compound, resources::ReactionRule};
+use crate::alchemy::{
+    components::*,
+    compound::{Compound, COMPOUND_WEIGHT},
+    reaction_table::ReactionTable,
+    resources::{
+        BrewingRng, BrewingWeighting, ReactionRule, ReactionRuleCompounds, ReactionRuleLayers,
+        ReactionRuleSet,
+    },
+    stirring::StirQuality,
+};
 use bevy::prelude::*;
 use rand::Rng;
+use std::collections::{BTreeSet, HashMap};
 
-/// Get all compounds that react under the given criteria according to the reaction rules.
+/// Get all compounds that react under the given criteria according to the reaction rules,
+/// in ascending order.
 /// `stir_method` and `heat` are optional,
 /// where None means there is no requirement for that criteria.
 /// For example, `stir_method=None` will only filter out compounds based on heat criteria.
 pub fn get_reactive_compounds(
-    reaction_rules: &Vec<ReactionRule>,
+    reaction_rules: &[ReactionRule],
     stir_method: Option<StirMethod>,
     heat: Option<Heat>,
 ) -> Vec<Compound> {
     reaction_rules
-        .clone()
+        .iter()
+        .filter(|rule| rule.applies(stir_method, heat))
+        .flat_map(|rule| rule.compounds())
+        .collect::<BTreeSet<Compound>>()
         .into_iter()
-        .filter(|rule| {
-            // Rustfmt seems to convert &&s between match objects to double references.
-            // So unfortunately using lets here.
-            let stir_match = match stir_method {
-                Some(sm) => match rule.stir_method {
-                    Some(rule_sm) => sm == rule_sm,
-                    None => true,
-                },
-                None => true,
-            };
-            let heat_match = match heat {
-                Some(h) => match rule.heat {
-                    Some(rule_h) => h == rule_h,
-                    None => true,
-                },
-                None => true,
-            };
-            stir_match && heat_match
-        })
-        .map(|rule| rule.compound)
         .collect::<Vec<Compound>>()
 }
 
 const COLLISION_CHANCE: f32 = 0.1;
 
-/// Assumes there will only ever be one cauldron.
-/// In this case, we could technically handle it as a Resource, but I prefer the ergonomics of
-/// having it represented by many components.
+/// The share of the usual collisions that still happen with the sloppiest stirring.
+const MIN_STIR_COLLISION_FACTOR: f32 = 0.5;
+
+/// The chance of a reactive compound colliding each brewing tick.
+/// Cauldrons stirred by gesture collide less the less consistent the stirring,
+/// down to `MIN_STIR_COLLISION_FACTOR` of the `COLLISION_CHANCE` at a `StirQuality` of 0.
+pub fn collision_chance(stir_quality: Option<&StirQuality>) -> f32 {
+    match stir_quality {
+        Some(StirQuality(quality)) => {
+            COLLISION_CHANCE
+                * (MIN_STIR_COLLISION_FACTOR
+                    + (1.0 - MIN_STIR_COLLISION_FACTOR) * quality.clamp(0.0, 1.0))
+        }
+        None => COLLISION_CHANCE,
+    }
+}
+
+/// Every cauldron brews its own contents, under its own heat and stir method.
+/// We could technically keep each cauldron's contents in a component of the cauldron,
+/// but I prefer the ergonomics of having each compound be an entity.
 pub fn brewing(
-    mut compound_query: Query<&mut Compound>,
-    cauldron_query: Query<(&Heat, &StirMethod), With<Cauldron>>,
+    mut compound_query: Query<(&mut Compound, &ContainedIn)>,
+    cauldron_query: Query<(Entity, &Heat, &StirMethod, Option<&StirQuality>), With<Cauldron>>,
     reaction_rules: Res<Vec<ReactionRule>>,
+    reaction_rule_compounds: Res<ReactionRuleCompounds>,
+    mut brewing_rng: ResMut<BrewingRng>,
+    brewing_weighting: Res<BrewingWeighting>,
+    mut reaction_table: ResMut<ReactionTable<COMPOUND_WEIGHT>>,
 ) {
-    if let Some((heat, stir_method)) = cauldron_query.iter().next() {
-        let reactive_compounds =
-            get_reactive_compounds(&reaction_rules, Some(*stir_method), Some(*heat));
+    let reactive_compounds = cauldron_query
+        .iter()
+        .map(|(cauldron, heat, stir_method, stir_quality)| {
+            (
+                cauldron,
+                (
+                    reaction_rule_compounds.reactive_compounds(
+                        &reaction_rules,
+                        Some(*stir_method),
+                        Some(*heat),
+                    ),
+                    collision_chance(stir_quality),
+                ),
+            )
+        })
+        .collect::<HashMap<Entity, (BTreeSet<&Compound>, f32)>>();
+
+    let rng = &mut brewing_rng.0;
+    // Collected so the rng is free to be used for the reactions themselves.
+    let mut colliding_compounds: HashMap<Entity, Vec<Mut<Compound>>> = HashMap::new();
+    for (compound, ContainedIn(container)) in compound_query.iter_mut() {
+        if let Some((reactive_compounds, collision_chance)) = reactive_compounds.get(container) {
+            if reactive_compounds.contains(&*compound) && rng.gen::<f32>() <= *collision_chance {
+                colliding_compounds
+                    .entry(*container)
+                    .or_default()
+                    .push(compound);
+            }
+        }
+    }
 
-        let mut rng = rand::thread_rng();
-        let mut colliding_compounds = compound_query
-            .iter_mut()
-            .filter(|compound| reactive_compounds.contains(compound))
-            .filter(|_| rng.gen::<f32>() <= COLLISION_CHANCE);
+    // Going through the cauldrons in query order, since the order of a HashMap isn't reproducible.
+    for (cauldron, _, _, _) in cauldron_query.iter() {
+        let mut colliding_compounds = colliding_compounds
+            .remove(&cauldron)
+            .unwrap_or_default()
+            .into_iter();
 
         while let (Some(mut left), Some(mut right)) =
             (colliding_compounds.next(), colliding_compounds.next())
         {
-            left.react(&mut right);
+            reaction_table.react(&mut left, &mut right, brewing_weighting.0.as_ref(), rng);
         }
     }
 }
+
+/// Reload the reaction rule layers whose files have changed since they were loaded.
+/// Files that fail validation are reported and skipped, so the previous rules stay in place.
+pub fn reloading_reaction_rule_layers(
+    mut reaction_rule_layers: ResMut<ReactionRuleLayers>,
+    mut reaction_rule_sets: ResMut<Assets<ReactionRuleSet>>,
+) {
+    for layer in reaction_rule_layers.0.iter_mut() {
+        match layer.reload_if_modified() {
+            Some(Ok(reaction_rule_set)) => {
+                reaction_rule_sets.set_untracked(&layer.handle, reaction_rule_set)
+            }
+            Some(Err(e)) => error!("Kept the previous reaction rules: {}", e),
+            None => {}
+        }
+    }
+}
+
+/// Replace the reaction rules whenever one of the rule set layers is (re)loaded.
+pub fn update_reaction_rules(
+    mut asset_events: EventReader<AssetEvent<ReactionRuleSet>>,
+    reaction_rule_layers: Res<ReactionRuleLayers>,
+    reaction_rule_sets: Res<Assets<ReactionRuleSet>>,
+    mut reaction_rules: ResMut<Vec<ReactionRule>>,
+    mut reaction_rule_compounds: ResMut<ReactionRuleCompounds>,
+) {
+    let changed = asset_events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => reaction_rule_layers
+            .0
+            .iter()
+            .any(|layer| layer.handle == *handle),
+        _ => false,
+    });
+
+    if changed {
+        let layers = reaction_rule_layers
+            .0
+            .iter()
+            .map(|layer| reaction_rule_sets.get(&layer.handle))
+            .collect::<Option<Vec<&ReactionRuleSet>>>();
+
+        // Wait for every layer, so that the rules are only ever swapped for a complete set.
+        if let Some(layers) = layers {
+            match ReactionRuleSet::from_layers(layers.into_iter().cloned()) {
+                Ok(reaction_rule_set) => {
+                    *reaction_rule_compounds = ReactionRuleCompounds::new(&reaction_rule_set.0);
+                    *reaction_rules = reaction_rule_set.0;
+                    info!("Reloaded {} reaction rules", reaction_rules.len());
+                }
+                Err(e) => error!("Kept the previous reaction rules: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::{pattern::CompoundPattern, resources::ReactionRuleLayer};
+    use bevy::{
+        app::Events,
+        ecs::schedule::{Stage, SystemStage},
+    };
+
+    fn count_in(world: &mut World, cauldron: Entity, compound: &Compound) -> usize {
+        world
+            .query::<(&Compound, &ContainedIn)>()
+            .iter(world)
+            .filter(|(c, ContainedIn(container))| *c == compound && *container == cauldron)
+            .count()
+    }
+
+    #[test]
+    fn test_sloppy_stirring_collides_less() {
+        assert_eq!(collision_chance(None), COLLISION_CHANCE);
+        assert_eq!(collision_chance(Some(&StirQuality(1.0))), COLLISION_CHANCE);
+        assert_eq!(
+            collision_chance(Some(&StirQuality(0.0))),
+            COLLISION_CHANCE * MIN_STIR_COLLISION_FACTOR
+        );
+        assert!(collision_chance(Some(&StirQuality(0.5))) < COLLISION_CHANCE);
+    }
+
+    #[test]
+    fn test_cached_compounds_match_rules() {
+        let reaction_rules = crate::alchemy::resources::load_reaction_rules().unwrap();
+        let reaction_rule_compounds = ReactionRuleCompounds::new(&reaction_rules);
+        for heat in [None, Some(Heat::Simmering), Some(Heat::Boiling)] {
+            for stir_method in [
+                None,
+                Some(StirMethod::ZeroStir),
+                Some(StirMethod::DoubleStir),
+            ] {
+                assert_eq!(
+                    reaction_rule_compounds
+                        .reactive_compounds(&reaction_rules, stir_method, heat)
+                        .into_iter()
+                        .cloned()
+                        .collect::<Vec<Compound>>(),
+                    get_reactive_compounds(&reaction_rules, stir_method, heat)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cauldrons_brew_independently() {
+        let seven_a: Compound = "7A".parse().unwrap();
+        let be: Compound = "BE".parse().unwrap();
+
+        let reaction_rules = vec![
+            ReactionRule {
+                compound: CompoundPattern::from(&seven_a),
+                heat: Some(Heat::Simmering),
+                ..Default::default()
+            },
+            ReactionRule {
+                compound: CompoundPattern::from(&be),
+                heat: Some(Heat::Simmering),
+                ..Default::default()
+            },
+        ];
+
+        let mut world = World::new();
+        world.insert_resource(ReactionRuleCompounds::new(&reaction_rules));
+        world.insert_resource(reaction_rules);
+        world.insert_resource(BrewingRng::seed_from_u64(0));
+        world.insert_resource(BrewingWeighting::default());
+        world.insert_resource(ReactionTable::<COMPOUND_WEIGHT>::default());
+
+        let simmering = world
+            .spawn()
+            .insert_bundle((Cauldron, Heat::Simmering, StirMethod::ZeroStir))
+            .id();
+        let boiling = world
+            .spawn()
+            .insert_bundle((Cauldron, Heat::Boiling, StirMethod::ZeroStir))
+            .id();
+        for cauldron in [simmering, boiling] {
+            for compound in [&seven_a, &be] {
+                for _ in 0..20 {
+                    world
+                        .spawn()
+                        .insert_bundle((compound.clone(), ContainedIn(cauldron)));
+                }
+            }
+        }
+
+        let mut stage = SystemStage::single(brewing.system());
+        for _ in 0..50 {
+            stage.run(&mut world);
+        }
+
+        assert!(count_in(&mut world, simmering, &seven_a) < 20);
+        assert_eq!(count_in(&mut world, boiling, &seven_a), 20);
+        assert_eq!(count_in(&mut world, boiling, &be), 20);
+    }
+
+    #[test]
+    fn test_changed_rules_are_reloaded() {
+        let rules = |heat| {
+            vec![ReactionRule {
+                compound: "7A".parse().unwrap(),
+                heat: Some(heat),
+                ..Default::default()
+            }]
+        };
+
+        let mut app = App::build();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(bevy::asset::AssetPlugin)
+            .add_asset::<ReactionRuleSet>();
+        let mut world = app.app.world;
+        let handle = world
+            .get_resource_mut::<Assets<ReactionRuleSet>>()
+            .unwrap()
+            .add(ReactionRuleSet(rules(Heat::Simmering)));
+        world.insert_resource(ReactionRuleLayers(vec![ReactionRuleLayer {
+            path: "test.json".into(),
+            handle: handle.clone(),
+            modified: None,
+        }]));
+        world.insert_resource(ReactionRuleCompounds::new(&rules(Heat::Simmering)));
+        world.insert_resource(rules(Heat::Simmering));
+
+        world
+            .get_resource_mut::<Assets<ReactionRuleSet>>()
+            .unwrap()
+            .set_untracked(&handle, ReactionRuleSet(rules(Heat::Boiling)));
+        world
+            .get_resource_mut::<Events<AssetEvent<ReactionRuleSet>>>()
+            .unwrap()
+            .send(AssetEvent::Modified {
+                handle: handle.clone_weak(),
+            });
+        SystemStage::single(update_reaction_rules.system()).run(&mut world);
+
+        assert_eq!(
+            *world.get_resource::<Vec<ReactionRule>>().unwrap(),
+            rules(Heat::Boiling)
+        );
+        assert_eq!(
+            *world.get_resource::<ReactionRuleCompounds>().unwrap(),
+            ReactionRuleCompounds::new(&rules(Heat::Boiling))
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use crate::alchemy::{
+    bands::{Band, Bands},
+    components::*,
+    design::DesignData,
+    BREWING_TICK,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The fire under a cauldron, and how hot the cauldron is.
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Temperature {
+    pub degrees: f32,
+    /// How much fuel is left to burn.
+    pub fuel: f32,
+    /// How much the bellows are fanning the fire, burning the fuel faster and hotter.
+    pub draft: f32,
+}
+
+impl Temperature {
+    /// An unlit fire, with the cauldron at `degrees`.
+    pub fn at(degrees: f32) -> Temperature {
+        Temperature {
+            degrees,
+            ..Default::default()
+        }
+    }
+}
+
+/// The temperature that a `Heat` starts at.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct HeatBand {
+    pub heat: Heat,
+    pub min_degrees: f32,
+}
+
+impl Band for HeatBand {
+    type Value = Heat;
+
+    fn value(&self) -> Heat {
+        self.heat
+    }
+
+    fn min(&self) -> f32 {
+        self.min_degrees
+    }
+}
+
+/// How fires heat cauldrons and how cauldrons cool,
+/// as authored in `assets/design/temperature.json`.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct TemperatureSettings {
+    /// The temperature that cauldrons cool down to.
+    pub ambient: f32,
+    /// The share of the difference from `ambient` lost each second.
+    pub cooling_rate: f32,
+    /// How much fuel burns each second with no draft.
+    pub fuel_burn_rate: f32,
+    /// How many degrees each unit of burnt fuel heats the cauldron by.
+    pub fuel_heat: f32,
+    /// How much draft each pump of the bellows adds.
+    pub bellows_draft: f32,
+    /// The share of the draft lost each second.
+    pub draft_decay: f32,
+    /// The heat bands, from the coolest up. Below the first band a cauldron has no heat.
+    pub bands: Bands<HeatBand>,
+}
+
+impl DesignData for TemperatureSettings {
+    const NAME: &'static str = "temperature settings";
+    const PATH: &'static str = "assets/design/temperature.json";
+}
+
+impl TemperatureSettings {
+    /// The heat of a cauldron at `degrees`, which is None below the first band.
+    pub fn heat(&self, degrees: f32) -> Option<Heat> {
+        self.bands.get(degrees)
+    }
+
+    /// Burn the fuel and cool the cauldron for `seconds` seconds.
+    pub fn step(&self, temperature: &mut Temperature, seconds: f32) {
+        let burnt =
+            (self.fuel_burn_rate * (1.0 + temperature.draft) * seconds).min(temperature.fuel);
+        temperature.fuel -= burnt;
+        temperature.degrees += burnt * self.fuel_heat;
+        temperature.degrees -=
+            (temperature.degrees - self.ambient) * (self.cooling_rate * seconds).min(1.0);
+        temperature.draft -= temperature.draft * (self.draft_decay * seconds).min(1.0);
+    }
+
+    /// Pump the bellows once.
+    pub fn pump(&self, temperature: &mut Temperature) {
+        temperature.draft += self.bellows_draft;
+    }
+}
+
+/// Send this to put more fuel on the fire under a cauldron with a `Temperature`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct AddFuel {
+    pub cauldron: Entity,
+    pub fuel: f32,
+}
+
+/// Send this whenever the player pumps the bellows under a cauldron with a `Temperature`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct PumpBellows(pub Entity);
+
+pub fn stoking(
+    mut fuel_events: EventReader<AddFuel>,
+    mut bellows_events: EventReader<PumpBellows>,
+    mut temperature_query: Query<&mut Temperature>,
+    temperature_settings: Res<TemperatureSettings>,
+) {
+    for AddFuel { cauldron, fuel } in fuel_events.iter() {
+        match temperature_query.get_mut(*cauldron) {
+            Ok(mut temperature) => temperature.fuel += fuel,
+            Err(_) => warn!("Tried to add fuel to {:?}, which has no fire", cauldron),
+        }
+    }
+    for PumpBellows(cauldron) in bellows_events.iter() {
+        match temperature_query.get_mut(*cauldron) {
+            Ok(mut temperature) => temperature_settings.pump(&mut temperature),
+            Err(_) => warn!(
+                "Tried to pump the bellows of {:?}, which has no fire",
+                cauldron
+            ),
+        }
+    }
+}
+
+/// Every cauldron with a `Temperature` heats or cools by a brewing tick,
+/// and gets the `Heat` of the band it ends up in.
+pub fn heating(
+    mut commands: Commands,
+    mut cauldron_query: Query<(Entity, &mut Temperature, Option<&mut Heat>)>,
+    temperature_settings: Res<TemperatureSettings>,
+) {
+    for (cauldron, mut temperature, heat) in cauldron_query.iter_mut() {
+        temperature_settings.step(&mut temperature, BREWING_TICK as f32);
+        match (temperature_settings.heat(temperature.degrees), heat) {
+            (Some(band), Some(mut heat)) => {
+                if *heat != band {
+                    *heat = band;
+                }
+            }
+            (Some(band), None) => {
+                commands.entity(cauldron).insert(band);
+            }
+            (None, Some(_)) => {
+                commands.entity(cauldron).remove::<Heat>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::design::parse_design;
+
+    fn settings() -> TemperatureSettings {
+        TemperatureSettings {
+            ambient: 20.0,
+            cooling_rate: 0.05,
+            fuel_burn_rate: 0.1,
+            fuel_heat: 35.0,
+            bellows_draft: 0.25,
+            draft_decay: 0.5,
+            bands: Bands::new(vec![
+                HeatBand {
+                    heat: Heat::Warm,
+                    min_degrees: 40.0,
+                },
+                HeatBand {
+                    heat: Heat::Simmering,
+                    min_degrees: 70.0,
+                },
+                HeatBand {
+                    heat: Heat::Boiling,
+                    min_degrees: 100.0,
+                },
+                HeatBand {
+                    heat: Heat::Scorching,
+                    min_degrees: 160.0,
+                },
+            ])
+            .unwrap(),
+        }
+    }
+
+    fn run(settings: &TemperatureSettings, temperature: &mut Temperature, seconds: u32) {
+        for _ in 0..seconds * 10 {
+            settings.step(temperature, 0.1);
+        }
+    }
+
+    #[test]
+    fn test_heat_bands() {
+        let settings = settings();
+        assert_eq!(settings.heat(20.0), None);
+        assert_eq!(settings.heat(40.0), Some(Heat::Warm));
+        assert_eq!(settings.heat(99.9), Some(Heat::Simmering));
+        assert_eq!(settings.heat(100.0), Some(Heat::Boiling));
+        assert_eq!(settings.heat(500.0), Some(Heat::Scorching));
+    }
+
+    #[test]
+    fn test_fire_heats_and_cools() {
+        let settings = settings();
+        let mut temperature = Temperature::at(settings.ambient);
+        temperature.fuel = 100.0;
+        run(&settings, &mut temperature, 60);
+        assert_eq!(settings.heat(temperature.degrees), Some(Heat::Simmering));
+
+        // Keeping the bellows going brings the cauldron to a boil.
+        for _ in 0..60 {
+            settings.pump(&mut temperature);
+            run(&settings, &mut temperature, 1);
+        }
+        assert_eq!(settings.heat(temperature.degrees), Some(Heat::Boiling));
+
+        // Then once the fuel runs out, it cools back down.
+        temperature.fuel = 0.0;
+        run(&settings, &mut temperature, 120);
+        assert_eq!(settings.heat(temperature.degrees), None);
+        assert!(temperature.draft < 0.01);
+    }
+
+    #[test]
+    fn test_band_order() {
+        let error = parse_design::<TemperatureSettings>(
+            r#"{
+                "ambient": 20, "cooling_rate": 0.05, "fuel_burn_rate": 0.1, "fuel_heat": 35,
+                "bellows_draft": 0.25, "draft_decay": 0.5,
+                "bands": [{"heat": "Boiling", "min_degrees": 100}, {"heat": "Simmering", "min_degrees": 70}]
+            }"#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("the Simmering band has to start above the band before it"));
+    }
+}
@@ -0,0 +1,487 @@
+use crate::alchemy::{
+    components::*,
+    compound::{Compound, CompoundError, ParseMode, COMPOUND_WEIGHT},
+    design::{display_diagnostics, locate_path},
+    pattern::{CompoundPattern, PatternError},
+    resources::ReactionRule,
+};
+use serde_json::{Map, Value};
+use std::{collections::BTreeSet, fmt, io, str::FromStr};
+use strum::IntoEnumIterator;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RuleError {
+    #[error("{message}")]
+    JsonError { message: String },
+    #[error("expected {expected}")]
+    TypeError { expected: &'static str },
+    #[error("missing field `{field}`")]
+    MissingFieldError { field: &'static str },
+    #[error("unknown field `{field}`")]
+    UnknownFieldError { field: String },
+    #[error("unknown variant `{value}`, expected one of {expected}")]
+    VariantError { value: String, expected: String },
+    #[error("{source}")]
+    CompoundError {
+        compound: String,
+        source: CompoundError,
+    },
+    #[error("{source}")]
+    PatternError {
+        compound: String,
+        source: PatternError,
+    },
+    #[error("pattern {compound:?} doesn't match any compound")]
+    UnmatchedError { compound: String },
+    #[error("compound {compound:?} has an alton weight of {weight}, expected {COMPOUND_WEIGHT}")]
+    WeightError { compound: String, weight: u32 },
+    #[error("compound {compound} already has a rule at $[{index}]")]
+    DuplicateError { compound: String, index: usize },
+    #[error("same heat and stir method as the rule for {compound} at $[{index}]")]
+    ConflictError { compound: String, index: usize },
+}
+
+/// A problem found in a reaction rules file, along with where it was found.
+#[derive(Debug, PartialEq)]
+pub struct RuleDiagnostic {
+    /// JSON path to the offending value, like `$[3].heat`.
+    pub path: String,
+    pub line: Option<usize>,
+    pub error: RuleError,
+}
+
+impl fmt::Display for RuleDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}, {}: {}", line, self.path, self.error),
+            None => write!(f, "{}: {}", self.path, self.error),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ReactionRulesError {
+    #[error("failed to read reaction rules: {0}")]
+    IoError(#[from] io::Error),
+    #[error("invalid reaction rules:\n{}", display_diagnostics(.0))]
+    InvalidError(Vec<RuleDiagnostic>),
+    #[error("reaction rules clash once layered:\n{}", display_diagnostics(.0))]
+    LayeredError(Vec<RuleDiagnostic>),
+    #[error("{path}: {source}")]
+    FileError {
+        path: String,
+        source: Box<ReactionRulesError>,
+    },
+}
+
+fn parse_variant<T>(value: &Value) -> Result<Option<T>, RuleError>
+where
+    T: FromStr + IntoEnumIterator + fmt::Display,
+{
+    match value {
+        Value::Null => Ok(None),
+        Value::String(s) => T::from_str(s)
+            .map(Some)
+            .map_err(|_| RuleError::VariantError {
+                value: s.clone(),
+                expected: T::iter()
+                    .map(|v| format!("`{}`", v))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            }),
+        _ => Err(RuleError::TypeError {
+            expected: "a string or null",
+        }),
+    }
+}
+
+/// Parse the compound pattern of a rule.
+/// Patterns that are written like a single compound are checked to be one.
+/// Single compounds may also be written leniently, like "D3A", which is read as "3AD".
+fn parse_compound(value: &Value) -> Result<CompoundPattern, RuleError> {
+    let s = match value {
+        Value::String(s) => s,
+        _ => {
+            return Err(RuleError::TypeError {
+                expected: "a string",
+            })
+        }
+    };
+    let compound = match Compound::from_str_with(s, ParseMode::Lenient) {
+        Err(CompoundError::SizeError { size }) => {
+            return Err(RuleError::WeightError {
+                compound: s.clone(),
+                weight: size,
+            })
+        }
+        compound => compound,
+    };
+    let pattern = match (CompoundPattern::from_str(s), compound) {
+        (Ok(pattern), _) => pattern,
+        (Err(_), Ok(compound)) => CompoundPattern::from(&compound),
+        // Report the more precise error of the two for what looks like a single compound.
+        (Err(_), Err(e)) if s.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ') => {
+            return Err(RuleError::CompoundError {
+                compound: s.clone(),
+                source: e,
+            })
+        }
+        (Err(e), Err(_)) => {
+            return Err(RuleError::PatternError {
+                compound: s.clone(),
+                source: e,
+            })
+        }
+    };
+    if pattern.all::<COMPOUND_WEIGHT>().next().is_none() {
+        return Err(RuleError::UnmatchedError {
+            compound: s.clone(),
+        });
+    }
+    Ok(pattern)
+}
+
+fn parse_weight(value: &Value) -> Result<Option<u32>, RuleError> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Number(n) => match n.as_u64() {
+            Some(n) if n <= u32::MAX as u64 => Ok(Some(n as u32)),
+            _ => Err(RuleError::TypeError {
+                expected: "a positive integer",
+            }),
+        },
+        _ => Err(RuleError::TypeError {
+            expected: "a positive integer or null",
+        }),
+    }
+}
+
+/// Parse a single rule, pushing every problem in it to `errors` as a field name and error.
+/// Returns None if the rule itself couldn't be parsed, unknown fields aside.
+fn parse_rule(
+    object: &Map<String, Value>,
+    errors: &mut Vec<(Option<String>, RuleError)>,
+) -> Option<ReactionRule> {
+    let null = Value::Null;
+    let field = |name: &str| object.get(name).unwrap_or(&null);
+
+    for key in object
+        .keys()
+        .filter(|key| !["compound", "heat", "stir_method", "weight"].contains(&key.as_str()))
+    {
+        errors.push((
+            Some(key.clone()),
+            RuleError::UnknownFieldError { field: key.clone() },
+        ));
+    }
+
+    let compound = match object.get("compound") {
+        Some(value) => parse_compound(value)
+            .map_err(|e| errors.push((Some("compound".to_string()), e)))
+            .ok(),
+        None => {
+            errors.push((None, RuleError::MissingFieldError { field: "compound" }));
+            None
+        }
+    };
+    let heat = parse_variant::<Heat>(field("heat"))
+        .map_err(|e| errors.push((Some("heat".to_string()), e)));
+    let stir_method = parse_variant::<StirMethod>(field("stir_method"))
+        .map_err(|e| errors.push((Some("stir_method".to_string()), e)));
+    let weight =
+        parse_weight(field("weight")).map_err(|e| errors.push((Some("weight".to_string()), e)));
+
+    Some(ReactionRule {
+        compound: compound?,
+        heat: heat.ok()?,
+        stir_method: stir_method.ok()?,
+        weight: weight.ok()?,
+    })
+}
+
+/// Check `rule` against the `checked` rules before it, each with its index and the compounds it covers,
+/// returning the first problem found along with the field it's in.
+fn check_overlap(
+    rule: &ReactionRule,
+    compounds: &BTreeSet<Compound>,
+    checked: &[(usize, ReactionRule, BTreeSet<Compound>)],
+) -> Option<(Option<&'static str>, RuleError)> {
+    // Patterns can cover the same compound without being written the same,
+    // like "*" and "7A", so they're compared by what they cover.
+    if let Some((other_index, shared)) =
+        checked
+            .iter()
+            .find_map(|(other_index, _, other_compounds)| {
+                Some((other_index, compounds.intersection(other_compounds).next()?))
+            })
+    {
+        return Some((
+            Some("compound"),
+            RuleError::DuplicateError {
+                compound: shared.to_string(),
+                index: *other_index,
+            },
+        ));
+    }
+    checked
+        .iter()
+        .find(|(_, other, _)| (other.heat, other.stir_method) == (rule.heat, rule.stir_method))
+        .map(|(other_index, other, _)| {
+            (
+                None,
+                RuleError::ConflictError {
+                    compound: other.compound.to_string(),
+                    index: *other_index,
+                },
+            )
+        })
+}
+
+/// Check rules layered together from several files for the problems
+/// that `validate_reaction_rules` checks for between the rules of a single file.
+/// The paths are into the layered rules, which aren't in any one file, so there are no lines.
+pub fn validate_layered_rules(rules: &[ReactionRule]) -> Vec<RuleDiagnostic> {
+    let mut checked = Vec::new();
+    let mut diagnostics = Vec::new();
+    for (index, rule) in rules.iter().enumerate() {
+        let compounds = rule.compounds().collect::<BTreeSet<Compound>>();
+        if let Some((field, error)) = check_overlap(rule, &compounds, &checked) {
+            diagnostics.push(RuleDiagnostic {
+                path: match field {
+                    Some(field) => format!("$[{}].{}", index, field),
+                    None => format!("$[{}]", index),
+                },
+                line: None,
+                error,
+            });
+        }
+        checked.push((index, rule.clone(), compounds));
+    }
+    diagnostics
+}
+
+/// Parse reaction rules from JSON, reporting every problem with them at once.
+pub fn validate_reaction_rules(source: &str) -> Result<Vec<ReactionRule>, Vec<RuleDiagnostic>> {
+    let value: Value = serde_json::from_str(source).map_err(|e| {
+        vec![RuleDiagnostic {
+            path: "$".to_string(),
+            line: Some(e.line()),
+            error: RuleError::JsonError {
+                message: e.to_string(),
+            },
+        }]
+    })?;
+    let elements = match value {
+        Value::Array(elements) => elements,
+        _ => {
+            return Err(vec![RuleDiagnostic {
+                path: "$".to_string(),
+                line: Some(1),
+                error: RuleError::TypeError {
+                    expected: "an array of reaction rules",
+                },
+            }])
+        }
+    };
+
+    let mut diagnostics = Vec::new();
+    let mut rules: Vec<(usize, ReactionRule, BTreeSet<Compound>)> = Vec::new();
+
+    for (index, element) in elements.iter().enumerate() {
+        let mut diagnose = |field: Option<&str>, error: RuleError| {
+            let path = match field {
+                Some(field) => format!("$[{}].{}", index, field),
+                None => format!("$[{}]", index),
+            };
+            diagnostics.push(RuleDiagnostic {
+                line: locate_path(source, &path),
+                path,
+                error,
+            })
+        };
+
+        let object = match element {
+            Value::Object(object) => object,
+            _ => {
+                diagnose(
+                    None,
+                    RuleError::TypeError {
+                        expected: "a reaction rule object",
+                    },
+                );
+                continue;
+            }
+        };
+
+        let mut errors = Vec::new();
+        let rule = parse_rule(object, &mut errors);
+        for (field, error) in errors {
+            diagnose(field.as_deref(), error);
+        }
+
+        if let Some(rule) = rule {
+            let compounds = rule.compounds().collect::<BTreeSet<Compound>>();
+            if let Some((field, error)) = check_overlap(&rule, &compounds, &rules) {
+                diagnose(field, error);
+            }
+            rules.push((index, rule, compounds));
+        }
+    }
+
+    if diagnostics.is_empty() {
+        Ok(rules.into_iter().map(|(_, rule, _)| rule).collect())
+    } else {
+        Err(diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_rules() {
+        let rules = validate_reaction_rules(
+            r#"[
+                {"compound": "7A", "heat": "Simmering", "stir_method": null},
+                {"compound": "BE", "heat": null, "stir_method": "DoubleStir", "weight": 3}
+            ]"#,
+        )
+        .expect("Rules should be valid");
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[1].weight, Some(3));
+    }
+
+    #[test]
+    fn test_every_problem_is_reported() {
+        let diagnostics = validate_reaction_rules(
+            r#"[
+	{
+		"compound": "D3Q",
+		"heat": "Simmering"
+	},
+	{
+		"compound": "ABC",
+		"heat": "Toasty",
+		"stir_method": "SingleStir"
+	},
+	{
+		"compound": "7A",
+		"heat": "Boiling",
+		"colour": "red"
+	},
+	{
+		"compound": "7A",
+		"heat": null
+	},
+	{
+		"compound": "BE",
+		"heat": "Boiling"
+	}
+]"#,
+        )
+        .expect_err("Rules should be invalid");
+
+        let found = diagnostics
+            .iter()
+            .map(|d| (d.path.as_str(), d.line))
+            .collect::<Vec<(&str, Option<usize>)>>();
+        assert_eq!(
+            found,
+            vec![
+                ("$[0].compound", Some(3)),
+                ("$[1].compound", Some(7)),
+                ("$[1].heat", Some(8)),
+                ("$[2].colour", Some(14)),
+                ("$[3].compound", Some(17)),
+                ("$[4]", Some(20)),
+            ]
+        );
+        assert_eq!(
+            diagnostics[1].error,
+            RuleError::WeightError {
+                compound: "ABC".to_string(),
+                weight: 6
+            }
+        );
+        assert!(matches!(
+            diagnostics[4].error,
+            RuleError::DuplicateError { index: 2, .. }
+        ));
+        assert!(matches!(
+            diagnostics[5].error,
+            RuleError::ConflictError { index: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_pattern_rules() {
+        let rules = validate_reaction_rules(
+            r#"[{"compound": "contains E | 7A", "heat": "Boiling", "stir_method": null}]"#,
+        )
+        .expect("Pattern rules should be valid");
+        assert!(rules[0].covers(&"BE".parse().unwrap()));
+
+        let diagnostics = validate_reaction_rules(
+            r#"[{"compound": "≥8A", "heat": null}, {"compound": "7A |", "heat": "Boiling"}]"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            diagnostics[0].error,
+            RuleError::UnmatchedError { .. }
+        ));
+        assert!(matches!(
+            diagnostics[1].error,
+            RuleError::PatternError { .. }
+        ));
+    }
+
+    #[test]
+    fn test_overlapping_patterns_are_duplicates() {
+        let diagnostics = validate_reaction_rules(
+            r#"[
+                {"compound": "*", "heat": "Boiling", "stir_method": null},
+                {"compound": "7A", "heat": "Simmering", "stir_method": null},
+                {"compound": "contains E", "heat": null, "stir_method": null}
+            ]"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            diagnostics
+                .into_iter()
+                .map(|d| (d.path, d.error))
+                .collect::<Vec<(String, RuleError)>>(),
+            vec![
+                (
+                    "$[1].compound".to_string(),
+                    RuleError::DuplicateError {
+                        compound: "7A".to_string(),
+                        index: 0
+                    }
+                ),
+                (
+                    "$[2].compound".to_string(),
+                    RuleError::DuplicateError {
+                        compound: "BE".to_string(),
+                        index: 0
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lenient_compounds_are_normalized() {
+        let rules = validate_reaction_rules(r#"[{"compound": "D A A A", "heat": null}]"#)
+            .expect("Lenient compounds should be valid");
+        assert_eq!(rules[0].compound.to_string(), "3AD");
+    }
+
+    #[test]
+    fn test_json_errors_have_lines() {
+        let diagnostics = validate_reaction_rules("[\n{\"compound\": }\n]").unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(2));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::alchemy::{
+    compound::{Alchemical, Compound, COMPOUND_WEIGHT},
+    element::Element,
+    resources::ReactionRule,
+    AltonWeighable,
+};
+use std::collections::HashMap;
+
+/// Decides how likely each possible outcome of a reaction between two alchemicals is.
+/// Weights are relative, they're only compared against the other outcomes of the same reaction.
+pub trait OutcomeWeighting<const W: u32>: Send + Sync {
+    fn weight(
+        &self,
+        reactants: (&Alchemical<W>, &Alchemical<W>),
+        products: (&Alchemical<W>, &Alchemical<W>),
+    ) -> f64;
+}
+
+/// Every outcome is equally likely, including the one where nothing changes.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct UniformWeighting;
+
+impl<const W: u32> OutcomeWeighting<W> for UniformWeighting {
+    fn weight(
+        &self,
+        _: (&Alchemical<W>, &Alchemical<W>),
+        _: (&Alchemical<W>, &Alchemical<W>),
+    ) -> f64 {
+        1.0
+    }
+}
+
+/// Weighs outcomes by the number of altons that have to move between the reactants to produce
+/// them, each moved alton multiplying the weight by `decay`.
+/// So a `decay` below 1 favors small rearrangements, and above 1 favors exotic ones.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct AltonDistanceWeighting {
+    pub decay: f64,
+}
+
+impl AltonDistanceWeighting {
+    /// Number of altons that leave `reactant` to become `product`.
+    /// Since both have the same weight, it's also the number of altons that enter it.
+    pub fn alton_distance<const W: u32>(reactant: &Alchemical<W>, product: &Alchemical<W>) -> u32 {
+        Element::iter()
+            .map(|e| {
+                reactant
+                    .element_count(e)
+                    .saturating_sub(product.element_count(e))
+                    * e.weight()
+            })
+            .sum()
+    }
+
+    /// Number of altons that have to move between the reactants to produce `products`.
+    /// Either product can come from either reactant, so the products swapping places,
+    /// which changes nothing in the cauldron, is no distance at all.
+    pub fn reaction_distance<const W: u32>(
+        reactants: (&Alchemical<W>, &Alchemical<W>),
+        products: (&Alchemical<W>, &Alchemical<W>),
+    ) -> u32 {
+        // Altons leaving one reactant enter the other, so each pairing counts them twice.
+        let straight = Self::alton_distance(reactants.0, products.0)
+            + Self::alton_distance(reactants.1, products.1);
+        let crossed = Self::alton_distance(reactants.0, products.1)
+            + Self::alton_distance(reactants.1, products.0);
+        straight.min(crossed) / 2
+    }
+}
+
+impl<const W: u32> OutcomeWeighting<W> for AltonDistanceWeighting {
+    fn weight(
+        &self,
+        reactants: (&Alchemical<W>, &Alchemical<W>),
+        products: (&Alchemical<W>, &Alchemical<W>),
+    ) -> f64 {
+        self.decay
+            .powi(Self::reaction_distance(reactants, products) as i32)
+    }
+}
+
+/// Weighs outcomes by the `weight` of the reaction rules of the products.
+/// Products without a rule, or with no weight in their rule, have a weight of 1.
+/// Where several rules with a weight cover a product, the first of them is used.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct RuleWeighting {
+    weights: HashMap<Compound, u32>,
+}
+
+impl RuleWeighting {
+    pub fn from_rules(reaction_rules: &[ReactionRule]) -> RuleWeighting {
+        let mut weights = HashMap::new();
+        for rule in reaction_rules {
+            if let Some(weight) = rule.weight {
+                for compound in rule.compounds() {
+                    weights.entry(compound).or_insert(weight);
+                }
+            }
+        }
+        RuleWeighting { weights }
+    }
+
+    fn compound_weight(&self, compound: &Compound) -> f64 {
+        self.weights.get(compound).copied().unwrap_or(1) as f64
+    }
+}
+
+impl OutcomeWeighting<COMPOUND_WEIGHT> for RuleWeighting {
+    fn weight(&self, _: (&Compound, &Compound), products: (&Compound, &Compound)) -> f64 {
+        self.compound_weight(products.0) * self.compound_weight(products.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy::compound::CompoundError;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_alton_distance() -> Result<(), CompoundError> {
+        let reactant: Compound = "2AE".parse()?;
+        assert_eq!(
+            AltonDistanceWeighting::alton_distance(&reactant, &"2AE".parse()?),
+            0
+        );
+        assert_eq!(
+            AltonDistanceWeighting::alton_distance(&reactant, &"BE".parse()?),
+            2
+        );
+        assert_eq!(
+            AltonDistanceWeighting::alton_distance(&reactant, &"3A2B".parse()?),
+            5
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reaction_distance_is_symmetric() -> Result<(), CompoundError> {
+        let (left, right): (Compound, Compound) = ("2AE".parse()?, "A3B".parse()?);
+        let products: (Compound, Compound) = ("BE".parse()?, "3A2B".parse()?);
+        assert_eq!(
+            AltonDistanceWeighting::reaction_distance((&left, &right), (&right, &left)),
+            0
+        );
+        assert_eq!(
+            AltonDistanceWeighting::reaction_distance((&left, &right), (&products.0, &products.1)),
+            AltonDistanceWeighting::reaction_distance((&left, &right), (&products.1, &products.0))
+        );
+
+        let weighting = AltonDistanceWeighting { decay: 0.5 };
+        assert_eq!(weighting.weight((&left, &right), (&right, &left)), 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zero_decay_never_changes_reactants() -> Result<(), CompoundError> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let weighting = AltonDistanceWeighting { decay: 0.0 };
+        let mut left: Compound = "2AE".parse()?;
+        let mut right: Compound = "A3B".parse()?;
+        let mut reactants = vec![left.clone(), right.clone()];
+        reactants.sort();
+        for _ in 0..20 {
+            left.react_weighted(&mut right, &weighting, &mut rng);
+            // Swapping places is no change, so it's as likely as staying put.
+            let mut products = vec![left.clone(), right.clone()];
+            products.sort();
+            assert_eq!(products, reactants);
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,26 @@
 use witchcraft::*;
 
 use bevy::prelude::*;
+use std::env;
 
+/// Pass `--rules PATH` (or `-r PATH`) to brew with other reaction rules.
+/// Giving it more than once layers each file on top of the last.
+/// Pass `--show PATTERN` to only show compounds matching a pattern, like "contains E".
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    let rank_display_pattern = args
+        .windows(2)
+        .find(|pair| pair[0] == "--show")
+        .map(|pair| {
+            pair[1]
+                .parse()
+                .unwrap_or_else(|e| panic!("Invalid --show pattern: {}", e))
+        })
+        .unwrap_or_default();
+
     App::build()
+        .insert_resource(alchemy::resources::ReactionRulePaths::from_args(&args))
+        .insert_resource(alchemy::debug::RankDisplayPattern(rank_display_pattern))
         .add_plugins(DefaultPlugins)
         .add_state(AppState::Brewing)
         .add_plugin(alchemy::debug::BrewingPluginDebug)
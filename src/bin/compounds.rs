@@ -0,0 +1,49 @@
+use csv::Writer;
+use std::{env, error::Error, io};
+use witchcraft::*;
+
+/// Lists every possible compound along with its reaction rules.
+/// Compounds without any rule never react, these are flagged as inert.
+/// Pass `--inert` or `-i` to only list those.
+/// Pass `--rules PATH` (or `-r PATH`) to list them for other reaction rules,
+/// giving it more than once to layer each file on top of the last.
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    let only_inert = args.contains(&"--inert".to_string()) || args.contains(&"-i".to_string());
+    let alchemy::resources::ReactionRulePaths(reaction_rule_paths) =
+        alchemy::resources::ReactionRulePaths::from_args(&args);
+    let reaction_rules = alchemy::resources::ReactionRuleSet::load_layered(&reaction_rule_paths)?.0;
+    let mut writer = Writer::from_writer(io::stdout());
+
+    writer.write_record(["compound", "inert", "heat", "stir_method"])?;
+
+    for compound in alchemy::compound::Compound::all() {
+        let rules = reaction_rules
+            .iter()
+            .filter(|rule| rule.covers(&compound))
+            .collect::<Vec<&alchemy::resources::ReactionRule>>();
+
+        if rules.is_empty() {
+            writer.write_record([
+                compound.to_string(),
+                "true".to_string(),
+                "".to_string(),
+                "".to_string(),
+            ])?;
+        } else if !only_inert {
+            for rule in rules {
+                writer.write_record([
+                    compound.to_string(),
+                    "false".to_string(),
+                    rule.heat.map_or("Any".to_string(), |h| h.to_string()),
+                    rule.stir_method
+                        .map_or("Any".to_string(), |s| s.to_string()),
+                ])?;
+            }
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
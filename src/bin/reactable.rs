@@ -1,40 +1,51 @@
 use csv::Writer;
-use std::{env, io};
+use std::{collections::HashSet, env, error::Error, io};
 use witchcraft::*;
 
-fn main() -> io::Result<()> {
+fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = env::args().collect();
     let anarchy = args.contains(&"--anarchy".to_string()) || args.contains(&"-a".to_string());
-    let reaction_rules = alchemy::resources::load_reaction_rules()?;
+    let alchemy::resources::ReactionRulePaths(reaction_rule_paths) =
+        alchemy::resources::ReactionRulePaths::from_args(&args);
+    let reaction_rules = alchemy::resources::ReactionRuleSet::load_layered(&reaction_rule_paths)?.0;
+    let mut reaction_table = alchemy::reaction_table::ReactionTable::default();
     let mut writer = Writer::from_writer(io::stdout());
 
+    // Every compound covered by the rules, along with the criteria of the first rule covering it.
+    let mut covered_compounds = Vec::new();
+    for rule in &reaction_rules {
+        for compound in rule.compounds() {
+            if !covered_compounds
+                .iter()
+                .any(|(covered, _, _)| *covered == compound)
+            {
+                covered_compounds.push((compound, rule.heat, rule.stir_method));
+            }
+        }
+    }
+
     let mut first_row = vec!["".to_string()];
-    for alchemy::resources::ReactionRule { compound, .. } in &reaction_rules {
+    for (compound, _, _) in &covered_compounds {
         first_row.push(compound.to_string())
     }
 
     writer.write_record(first_row)?;
 
-    for alchemy::resources::ReactionRule {
-        compound: row_compound,
-        stir_method,
-        heat,
-    } in &reaction_rules
-    {
+    for (row_compound, heat, stir_method) in &covered_compounds {
         let reactive_compounds =
             alchemy::systems::get_reactive_compounds(&reaction_rules, *stir_method, *heat);
 
         let mut row = vec![row_compound.to_string()];
 
-        for alchemy::resources::ReactionRule {
-            compound: col_compound,
-            ..
-        } in &reaction_rules
-        {
+        for (col_compound, _, _) in &covered_compounds {
             if reactive_compounds.contains(col_compound) || anarchy {
                 row.push(
                     utils::reduce_reverse_pairs(
-                        row_compound.set_of_possible_reactions(col_compound),
+                        reaction_table
+                            .possible_reactions(row_compound, col_compound)
+                            .iter()
+                            .cloned()
+                            .collect::<HashSet<_>>(),
                     )
                     .into_iter()
                     .filter(|(left, right)| {
@@ -0,0 +1,35 @@
+use std::{env, fs, process};
+use witchcraft::*;
+
+/// Checks reaction rules files, reporting every problem found in them.
+/// Checks `assets/design/reaction_rules.json` if no paths are given.
+/// Exits with a nonzero status if any file has problems.
+fn main() {
+    let mut paths: Vec<String> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        paths.push("assets/design/reaction_rules.json".to_string());
+    }
+
+    let mut failed = false;
+    for path in paths {
+        match fs::read_to_string(&path) {
+            Ok(data) => match alchemy::validation::validate_reaction_rules(&data) {
+                Ok(reaction_rules) => println!("{}: {} rules ok", path, reaction_rules.len()),
+                Err(diagnostics) => {
+                    failed = true;
+                    for diagnostic in diagnostics {
+                        eprintln!("{}: {}", path, diagnostic);
+                    }
+                }
+            },
+            Err(e) => {
+                failed = true;
+                eprintln!("{}: {}", path, e);
+            }
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,222 @@
+//! Runs the brewing reaction loop without a window.
+//!
+//! Usage:
+//! `simulator [--seed N] [--ticks N] [--heat TICK:HEAT]... [--stir TICK:STIR]...
+//! [--weighting uniform|rules|distance:DECAY] [--rules PATH]... [--snapshots csv|json] POPULATION...`
+//!
+//! The population is given as a mixture of compounds, like `"20×A3B + 30×7A + 30×BE"`.
+//! Each population argument is added to the cauldron, so `20xA3B 30x7A 30xBE` works too.
+//! `--heat 10:Boiling` boils the cauldron from tick 10 onwards, `--heat 20:None` removes the heat.
+//! `--stir 10:DoubleStir` changes the stir method from tick 10 onwards.
+//! The cauldron starts with no heat and `ZeroStir`.
+//! `--weighting` picks how likely reaction outcomes are, `distance:0.5` being an
+//! `AltonDistanceWeighting` with a decay of 0.5. It defaults to `uniform`.
+//! `--rules` replaces the default reaction rules, each one given is layered on top of the last.
+use bevy::prelude::*;
+use csv::Writer;
+use serde::Serialize;
+use std::{collections::BTreeMap, env, error::Error, io, str::FromStr};
+use witchcraft::*;
+
+use alchemy::{
+    components::{Cauldron, ContainedIn, Heat, StirMethod},
+    compound::{Compound, COMPOUND_WEIGHT},
+    mixture::Mixture,
+    reaction_table::ReactionTable,
+    resources::{
+        BrewingRng, BrewingWeighting, ReactionRule, ReactionRuleCompounds, ReactionRulePaths,
+        ReactionRuleSet,
+    },
+    weighting::{AltonDistanceWeighting, RuleWeighting, UniformWeighting},
+};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum SnapshotFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Snapshot {
+    tick: u32,
+    compounds: BTreeMap<String, u32>,
+}
+
+struct Options {
+    seed: Option<u64>,
+    ticks: u32,
+    heat_changes: Vec<(u32, Option<Heat>)>,
+    stir_changes: Vec<(u32, StirMethod)>,
+    weighting: String,
+    reaction_rule_paths: ReactionRulePaths,
+    snapshots: Option<SnapshotFormat>,
+    population: Mixture,
+}
+
+const USAGE: &str = "simulator [--seed N] [--ticks N] [--heat TICK:HEAT]... [--stir TICK:STIR]... \
+    [--weighting uniform|rules|distance:DECAY] [--rules PATH]... [--snapshots csv|json] POPULATION...";
+
+fn parse_scheduled<T, F>(arg: &str, parse: F) -> Result<(u32, T), Box<dyn Error>>
+where
+    F: Fn(&str) -> Result<T, Box<dyn Error>>,
+{
+    let (tick, value) = arg
+        .split_once(':')
+        .ok_or_else(|| format!("expected TICK:VALUE, got {}", arg))?;
+    Ok((tick.parse()?, parse(value)?))
+}
+
+fn parse_options() -> Result<Options, Box<dyn Error>> {
+    let mut options = Options {
+        seed: None,
+        ticks: 100,
+        heat_changes: Vec::new(),
+        stir_changes: Vec::new(),
+        weighting: "uniform".to_string(),
+        reaction_rule_paths: ReactionRulePaths(Vec::new()),
+        snapshots: None,
+        population: Mixture::default(),
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--seed" | "-s" => options.seed = Some(value()?.parse()?),
+            "--ticks" | "-t" => options.ticks = value()?.parse()?,
+            "--heat" => options.heat_changes.push(parse_scheduled(&value()?, |h| {
+                Ok(match h {
+                    "None" => None,
+                    h => Some(Heat::from_str(h)?),
+                })
+            })?),
+            "--stir" => options.stir_changes.push(parse_scheduled(&value()?, |s| {
+                Ok(StirMethod::from_str(s)?)
+            })?),
+            "--weighting" | "-w" => options.weighting = value()?,
+            "--rules" | "-r" => options.reaction_rule_paths.0.push(value()?.into()),
+            "--snapshots" => {
+                options.snapshots = Some(match value()?.as_str() {
+                    "csv" => SnapshotFormat::Csv,
+                    "json" => SnapshotFormat::Json,
+                    other => return Err(format!("unknown snapshot format: {}", other).into()),
+                })
+            }
+            arg if arg.starts_with('-') => {
+                return Err(format!("unknown option {}, usage: {}", arg, USAGE).into())
+            }
+            _ => options.population.extend(arg.parse()?),
+        }
+    }
+
+    if options.reaction_rule_paths.0.is_empty() {
+        options.reaction_rule_paths = ReactionRulePaths::default();
+    }
+
+    Ok(options)
+}
+
+fn parse_weighting(
+    weighting: &str,
+    reaction_rules: &[ReactionRule],
+) -> Result<BrewingWeighting, Box<dyn Error>> {
+    Ok(BrewingWeighting(match weighting.split_once(':') {
+        Some(("distance", decay)) => Box::new(AltonDistanceWeighting {
+            decay: decay.parse()?,
+        }),
+        None if weighting == "rules" => Box::new(RuleWeighting::from_rules(reaction_rules)),
+        None if weighting == "uniform" => Box::new(UniformWeighting),
+        _ => return Err(format!("unknown weighting: {}", weighting).into()),
+    }))
+}
+
+fn snapshot(world: &mut World, tick: u32) -> Snapshot {
+    let mut compounds = BTreeMap::new();
+    for compound in world.query::<&Compound>().iter(world) {
+        *compounds.entry(compound.to_string()).or_insert(0) += 1;
+    }
+    Snapshot { tick, compounds }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = parse_options()?;
+
+    let reaction_rules = ReactionRuleSet::load_layered(&options.reaction_rule_paths.0)?.0;
+    let weighting = parse_weighting(&options.weighting, &reaction_rules)?;
+
+    let mut app = App::build();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(ReactionRuleCompounds::new(&reaction_rules))
+        .insert_resource(reaction_rules)
+        .insert_resource(weighting)
+        .init_resource::<ReactionTable<COMPOUND_WEIGHT>>()
+        .insert_resource(match options.seed {
+            Some(seed) => BrewingRng::seed_from_u64(seed),
+            None => BrewingRng::default(),
+        })
+        // Added without the FixedTimestep of the BrewingPlugin, so every update is a tick.
+        .add_system(alchemy::systems::brewing.system());
+    let mut app = app.app;
+
+    let cauldron = app
+        .world
+        .spawn()
+        .insert(Cauldron)
+        .insert(StirMethod::ZeroStir)
+        .id();
+    for compound in options.population.compounds() {
+        app.world
+            .spawn()
+            .insert(compound.clone())
+            .insert(ContainedIn(cauldron));
+    }
+
+    let mut snapshots = Vec::new();
+    for tick in 0..options.ticks {
+        for (_, heat) in options.heat_changes.iter().filter(|(t, _)| *t == tick) {
+            let mut cauldron = app.world.entity_mut(cauldron);
+            match heat {
+                Some(heat) => drop(cauldron.insert(*heat)),
+                None => drop(cauldron.remove::<Heat>()),
+            }
+        }
+        for (_, stir_method) in options.stir_changes.iter().filter(|(t, _)| *t == tick) {
+            app.world.entity_mut(cauldron).insert(*stir_method);
+        }
+
+        app.update();
+
+        if options.snapshots.is_some() {
+            snapshots.push(snapshot(&mut app.world, tick));
+        }
+    }
+
+    match options.snapshots {
+        Some(SnapshotFormat::Csv) => {
+            let mut writer = Writer::from_writer(io::stdout());
+            writer.write_record(["tick", "compound", "count"])?;
+            for Snapshot { tick, compounds } in &snapshots {
+                for (compound, count) in compounds {
+                    writer.write_record([tick.to_string(), compound.clone(), count.to_string()])?;
+                }
+            }
+            writer.flush()?;
+        }
+        Some(SnapshotFormat::Json) => println!("{}", serde_json::to_string_pretty(&snapshots)?),
+        None => {
+            let mut compound_counts = snapshot(&mut app.world, options.ticks)
+                .compounds
+                .into_iter()
+                .collect::<Vec<(String, u32)>>();
+            compound_counts.sort_by(|(s1, v1), (s2, v2)| v2.cmp(v1).then_with(|| s1.cmp(s2)));
+            for (compound, count) in compound_counts {
+                println!("{} - {}", count, compound);
+            }
+        }
+    }
+
+    Ok(())
+}